@@ -0,0 +1,63 @@
+extern crate som;
+
+use som::compiler::{Lexer, SourceLexer};
+use std::hint::black_box;
+use std::io::BufRead;
+use std::time::Instant;
+
+fn large_class() -> String {
+    let mut source = String::from("Large = (\n");
+    for i in 0..2000 {
+        source.push_str(&format!(
+            "    method{} = ( | a b | a := #(1 2.5 16r1F 'item{}' $x). b := a collect: [ :each | each printString , 'it''s' ]. ^ b inject: 0 into: [ :sum :each | sum + each size ] )\n",
+            i, i
+        ));
+    }
+
+    source.push_str("    json = ( ^ '");
+    for i in 0..1000 {
+        source.push_str(&format!("{{\"key{}\": [1, 2, 3], \"value\": \"text\"}}, ", i));
+    }
+
+    source.push_str("' )\n)\n");
+    source
+}
+
+fn line_buffered(source: &str) -> usize {
+    let mut reader = source.as_bytes();
+    let mut line = String::new();
+    let mut characters = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).unwrap() == 0 {
+            return characters;
+        }
+
+        let mut position = 0;
+        while let Some(c) = line.chars().nth(position) {
+            black_box(c);
+            position += 1;
+            characters += 1;
+        }
+    }
+}
+
+fn measure<F: FnMut() -> usize>(name: &str, unit: &str, iterations: u32, mut f: F) {
+    let start = Instant::now();
+    let mut count = 0;
+    for _ in 0..iterations {
+        count = black_box(f());
+    }
+
+    let elapsed = start.elapsed() / iterations;
+    println!("{:<14} {:>8} {:<10} {:>12?}/iter", name, count, unit, elapsed);
+}
+
+fn main() {
+    let source = large_class();
+    println!("source: {} bytes", source.len());
+
+    measure("line-buffered", "characters", 5, || line_buffered(&source));
+    measure("reader", "tokens", 5, || Lexer::new(source.as_bytes()).count());
+    measure("source", "tokens", 5, || SourceLexer::new(&source).count());
+}
@@ -0,0 +1,189 @@
+extern crate som;
+
+use som::compiler::lexer::BorrowedItem;
+use som::compiler::printer::print_class;
+use som::compiler::{BorrowedToken, Dialect, Parser, ParserOptions, SourceLexer, Symbol};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+const CONTEXT: usize = 3;
+
+enum Edit<'a> {
+    Keep(&'a str),
+    Remove(&'a str),
+    Insert(&'a str),
+}
+
+fn dialect(name: &str) -> Option<Dialect> {
+    match name {
+        "som-java" => Some(Dialect::SomJava),
+        "som-cpp" => Some(Dialect::SomCpp),
+        "trufflesom" => Some(Dialect::TruffleSom),
+        "somns-lite" => Some(Dialect::SomNsLite),
+        _ => None,
+    }
+}
+
+fn count_comments(source: &str, options: &ParserOptions) -> usize {
+    SourceLexer::new(source)
+        .with_options(options.clone())
+        .with_trivia()
+        .filter(|item| matches!(*item, Ok(BorrowedItem(BorrowedToken(Symbol::Comment, _), _))))
+        .count()
+}
+
+fn format(filename: &str, source: &str, options: &ParserOptions) -> Result<String, String> {
+    let mut parser = Parser::new(source.as_bytes(), filename).with_options(options.clone()).with_trivia();
+    let class = parser.parse_class().map_err(|e| format!("{}: {:?}", filename, parser.interner().debug(&e)))?;
+
+    let formatted = print_class(&class, parser.interner(), options).map_err(|e| format!("{}: {:?}", filename, e))?;
+    if count_comments(&formatted, options) != count_comments(source, options) {
+        return Err(format!("{}: only comments before the class and before each method are preserved, refusing to drop the others", filename));
+    }
+
+    Ok(formatted)
+}
+
+fn edits<'a>(before: &[&'a str], after: &[&'a str]) -> Vec<Edit<'a>> {
+    let mut common = vec![vec![0; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            common[i][j] = if before[i] == after[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut edits = vec![];
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            edits.push(Edit::Keep(before[i]));
+            i += 1;
+            j += 1;
+        } else if i < before.len() && (j == after.len() || common[i + 1][j] >= common[i][j + 1]) {
+            edits.push(Edit::Remove(before[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(after[j]));
+            j += 1;
+        }
+    }
+
+    edits
+}
+
+fn unified_diff(filename: &str, before: &str, after: &str) -> String {
+    let before_lines: Vec<_> = before.split_inclusive('\n').collect();
+    let after_lines: Vec<_> = after.split_inclusive('\n').collect();
+    let edits = edits(&before_lines, &after_lines);
+
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (index, edit) in edits.iter().enumerate() {
+        if let Edit::Keep(_) = *edit {
+            continue;
+        }
+
+        let start = index.saturating_sub(CONTEXT);
+        let end = (index + CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(hunk) if hunk.1 >= start => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", filename, filename);
+    for (start, end) in hunks {
+        let old_line = 1 + edits[..start].iter().filter(|edit| !matches!(**edit, Edit::Insert(_))).count();
+        let new_line = 1 + edits[..start].iter().filter(|edit| !matches!(**edit, Edit::Remove(_))).count();
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|edit| !matches!(**edit, Edit::Insert(_))).count();
+        let new_count = hunk.iter().filter(|edit| !matches!(**edit, Edit::Remove(_))).count();
+        diff += &format!("@@ -{},{} +{},{} @@\n", old_line, old_count, new_line, new_count);
+        for edit in hunk {
+            let (prefix, line) = match *edit {
+                Edit::Keep(line) => (' ', line),
+                Edit::Remove(line) => ('-', line),
+                Edit::Insert(line) => ('+', line),
+            };
+
+            diff.push(prefix);
+            diff += line;
+            if !line.ends_with('\n') {
+                diff += "\n\\ No newline at end of file\n";
+            }
+        }
+    }
+
+    diff
+}
+
+fn read_source(filename: &str) -> Result<String, String> {
+    let mut source = String::new();
+    let result = if filename == "-" {
+        io::stdin().read_to_string(&mut source).map(|_| source)
+    } else {
+        fs::read_to_string(filename)
+    };
+
+    result.map_err(|e| format!("Unable to read {}: {}", filename, e))
+}
+
+fn run(filename: &str, check: bool, options: &ParserOptions) -> Result<bool, String> {
+    let source = read_source(filename)?;
+    let formatted = format(filename, &source, options)?;
+    if check {
+        if formatted != source {
+            print!("{}", unified_diff(filename, &source, &formatted));
+            return Ok(false);
+        }
+    } else if filename == "-" {
+        io::stdout().write_all(formatted.as_bytes()).map_err(|e| e.to_string())?;
+    } else if formatted != source {
+        fs::write(filename, formatted).map_err(|e| format!("Unable to write {}: {}", filename, e))?;
+    }
+
+    Ok(true)
+}
+
+fn main() {
+    let mut check = false;
+    let mut options = ParserOptions::default();
+    let mut filenames = vec![];
+    for argument in env::args().skip(1) {
+        match argument.as_str() {
+            "--check" => check = true,
+            _ if argument.starts_with("--dialect=") => match dialect(&argument["--dialect=".len()..]) {
+                Some(dialect) => options = dialect.options(),
+                None => {
+                    eprintln!("Unknown dialect {} (expected som-java, som-cpp, trufflesom or somns-lite)", &argument["--dialect=".len()..]);
+                    process::exit(2);
+                }
+            },
+            _ => filenames.push(argument),
+        }
+    }
+
+    if filenames.is_empty() {
+        eprintln!("Usage: som-fmt [--check] [--dialect=<name>] <file>... (use - for stdin)");
+        process::exit(2);
+    }
+
+    let mut status = 0;
+    for filename in &filenames {
+        match run(filename, check, &options) {
+            Ok(true) => {}
+            Ok(false) => status = status.max(1),
+            Err(e) => {
+                eprintln!("{}", e);
+                status = 2;
+            }
+        }
+    }
+
+    process::exit(status);
+}
@@ -2,24 +2,59 @@
 
 extern crate som;
 
+use som::compiler::check::check;
 use som::compiler::Parser;
 use std::env;
-use std::fs::File;
-use std::io::BufReader;
+use std::fs;
+use std::process;
 
 #[allow(dead_code)]
 fn main() {
-    let filename = match env::args().nth(1) {
+    let mut run_checks = false;
+    let mut trivia = false;
+    let mut filename = None;
+    for argument in env::args().skip(1) {
+        match argument.as_str() {
+            "--check" => run_checks = true,
+            "--trivia" => trivia = true,
+            _ => filename = Some(argument),
+        }
+    }
+
+    let filename = match filename {
         Some(f) => f,
         None => panic!("Must provide file to parse"),
     };
 
-    let file = match File::open(&filename) {
-        Ok(f) => f,
+    let source = match fs::read_to_string(&filename) {
+        Ok(source) => source,
         Err(e) => panic!("Unable to open {}: {:?}", filename, e),
     };
 
-    let reader = BufReader::new(file);
-    let mut parser = Parser::new(reader, filename);
-    println!("{:#?}", parser.parse_class().unwrap());
+    let mut parser = Parser::new(source.as_bytes(), &filename);
+    if trivia {
+        parser = parser.with_trivia();
+    }
+
+    let class = parser.parse_class();
+    let interner = parser.into_interner();
+    let class = match class {
+        Ok(class) => class,
+        Err(e) => panic!("{:?}", interner.debug(&e)),
+    };
+
+    if !run_checks {
+        println!("{:#?}", interner.debug(&class));
+        return;
+    }
+
+    let diagnostics = check(&class, &interner);
+    for diagnostic in &diagnostics {
+        let location = diagnostic.location();
+        println!("{}:{}:{}: {:?}", filename, location.line(), location.column(), interner.debug(diagnostic));
+    }
+
+    if !diagnostics.is_empty() {
+        process::exit(1);
+    }
 }
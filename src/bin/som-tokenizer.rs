@@ -18,8 +18,11 @@ fn main() {
     };
 
     let reader = BufReader::new(file);
-    let lexer = Lexer::new(reader);
-    for token in lexer {
-        println!("{:?}", token);
+    let mut lexer = Lexer::new(reader);
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(item) => println!("{:?}", lexer.interner().debug(&item)),
+            Err(e) => println!("Error: {:?}", e),
+        }
     }
 }
@@ -1,41 +1,457 @@
+use num_bigint::BigInt;
 use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::ops::Index;
+use std::slice;
+use std::vec;
+
+pub use compiler::interner::Name;
+use compiler::interner::Interner;
+pub use util::location::{Location, Span};
+
+use compiler::visit::{walk_block, walk_expression, Visitor};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Block {
-    parameters: Vec<String>,
-    locals: Vec<String>,
-    body: Vec<Expression>,
+    pub parameters: Vec<Name>,
+    pub parameter_spans: Vec<Span>,
+    pub locals: Vec<Name>,
+    pub body: Vec<Expression>,
+    pub span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Class {
-    pub name: String,
-    pub superclass: String,
-    pub instance_methods: HashMap<String, Method>,
-    pub instance_variables: Vec<String>,
-    pub class_methods: HashMap<String, Method>,
-    pub class_variables: Vec<String>,
+    pub name: Name,
+    pub superclass: Superclass,
+    pub instance_methods: MethodTable,
+    pub instance_variables: Vec<Name>,
+    pub class_methods: MethodTable,
+    pub class_variables: Vec<Name>,
+    pub comments: Vec<String>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub enum Expression {
-    Assignment { variables: Vec<String>, value: Box<Expression> },
-    BinaryMessage { message: String, left: Box<Expression>, right: Box<Expression> },
-    Block { parameters: Vec<String>, locals: Vec<String>, body: Vec<Expression> },
-    KeywordMessage { message: String, receiver: Box<Expression>, parameters: Vec<Expression> },
+pub enum ExpressionKind {
+    Assignment { variables: Vec<Name>, variable_spans: Vec<Span>, value: Box<Expression> },
+    BinaryMessage { message: Name, left: Box<Expression>, right: Box<Expression> },
+    Block(Block),
+    Cascade { receiver: Box<Expression>, messages: Vec<Message> },
+    KeywordMessage { message: Name, receiver: Box<Expression>, parameters: Vec<Expression> },
+    Error,
+    LiteralArray(Vec<Expression>),
+    LiteralBigInteger(BigInt),
     LiteralBoolean(bool),
+    LiteralCharacter(char),
     LiteralDouble(f64),
     LiteralInteger(i64),
     LiteralNil,
     LiteralString(String),
-    LiteralSymbol(String),
+    LiteralSymbol(Name),
     Return(Box<Expression>),
-    UnaryMessage { message: String, receiver: Box<Expression> },
-    Variable(String),
+    UnaryMessage { message: Name, receiver: Box<Expression> },
+    Variable(Name),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub selector: Name,
+    pub arguments: Vec<Expression>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Superclass {
+    None,
+    Implicit,
+    Named(Name),
+}
+
+#[derive(Clone, Default, PartialEq)]
+pub struct MethodTable {
+    methods: Vec<Method>,
+    index: HashMap<Name, usize>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Method {
-    Primitive { name: String, parameters: Vec<String> },
-    Native { name: String, parameters: Vec<String>, locals: Vec<String>, body: Vec<Expression> }
+    Primitive { name: Name, parameters: Vec<Name>, parameter_spans: Vec<Span>, comments: Vec<String>, span: Span },
+    Native { name: Name, body: Block, comments: Vec<String>, span: Span },
+}
+
+struct FreeVariables {
+    scopes: Vec<Vec<Name>>,
+    free: Vec<Name>,
+}
+
+impl FreeVariables {
+    fn reference(&mut self, name: Name) {
+        if !self.scopes.iter().any(|scope| scope.contains(&name)) && !self.free.contains(&name) {
+            self.free.push(name);
+        }
+    }
+}
+
+impl Visitor for FreeVariables {
+    fn visit_block(&mut self, block: &Block) {
+        self.scopes.push(block.parameters.iter().chain(&block.locals).cloned().collect());
+        walk_block(self, block);
+        self.scopes.pop();
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression.kind {
+            ExpressionKind::Variable(ref name) => self.reference(*name),
+            ExpressionKind::Assignment { ref variables, .. } => {
+                for variable in variables {
+                    self.reference(*variable);
+                }
+            }
+            _ => {}
+        }
+
+        walk_expression(self, expression);
+    }
+}
+
+struct NestedFreeVariables(Vec<Name>);
+
+impl Visitor for NestedFreeVariables {
+    fn visit_block(&mut self, block: &Block) {
+        self.0.extend(block.free_variables());
+    }
+}
+
+impl Block {
+    pub fn new(parameters: Vec<Name>, parameter_spans: Vec<Span>, locals: Vec<Name>, body: Vec<Expression>, span: Span) -> Block {
+        Block { parameters, parameter_spans, locals, body, span }
+    }
+
+    pub fn binds(&self, name: Name) -> bool {
+        self.parameters.contains(&name) || self.locals.contains(&name)
+    }
+
+    pub fn free_variables(&self) -> Vec<Name> {
+        let mut visitor = FreeVariables { scopes: vec![], free: vec![] };
+        visitor.visit_block(self);
+        visitor.free
+    }
+
+    pub fn captured_variables(&self) -> Vec<Name> {
+        let mut nested = NestedFreeVariables(vec![]);
+        walk_block(&mut nested, self);
+        self.parameters.iter().chain(&self.locals).filter(|name| nested.0.contains(name)).cloned().collect()
+    }
+
+    pub fn without_spans(&self) -> Block {
+        Block {
+            parameters: self.parameters.clone(),
+            parameter_spans: vec![Span::default(); self.parameters.len()],
+            locals: self.locals.clone(),
+            body: self.body.iter().map(Expression::without_spans).collect(),
+            span: Span::default(),
+        }
+    }
+}
+
+impl Class {
+    pub fn without_spans(&self) -> Class {
+        let strip = |methods: &MethodTable| MethodTable {
+            methods: methods.iter().map(Method::without_spans).collect(),
+            index: methods.index.clone(),
+        };
+
+        Class {
+            name: self.name,
+            superclass: self.superclass.clone(),
+            instance_methods: strip(&self.instance_methods),
+            instance_variables: self.instance_variables.clone(),
+            class_methods: strip(&self.class_methods),
+            class_variables: self.class_variables.clone(),
+            comments: self.comments.clone(),
+            span: Span::default(),
+        }
+    }
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Expression {
+        Expression { kind, span }
+    }
+
+    pub fn without_spans(&self) -> Expression {
+        let strip = |expression: &Expression| Box::new(expression.without_spans());
+        let strip_all = |expressions: &[Expression]| expressions.iter().map(Expression::without_spans).collect();
+
+        let kind = match self.kind {
+            ExpressionKind::Assignment { ref variables, ref value, .. } => ExpressionKind::Assignment {
+                variables: variables.clone(),
+                variable_spans: vec![Span::default(); variables.len()],
+                value: strip(value),
+            },
+            ExpressionKind::BinaryMessage { ref message, ref left, ref right } => ExpressionKind::BinaryMessage {
+                message: *message,
+                left: strip(left),
+                right: strip(right),
+            },
+            ExpressionKind::Block(ref block) => ExpressionKind::Block(block.without_spans()),
+            ExpressionKind::Cascade { ref receiver, ref messages } => ExpressionKind::Cascade {
+                receiver: strip(receiver),
+                messages: messages.iter().map(|message| Message {
+                    selector: message.selector,
+                    arguments: strip_all(&message.arguments),
+                }).collect(),
+            },
+            ExpressionKind::KeywordMessage { ref message, ref receiver, ref parameters } => ExpressionKind::KeywordMessage {
+                message: *message,
+                receiver: strip(receiver),
+                parameters: strip_all(parameters),
+            },
+            ExpressionKind::LiteralArray(ref values) => ExpressionKind::LiteralArray(strip_all(values)),
+            ExpressionKind::Return(ref value) => ExpressionKind::Return(strip(value)),
+            ExpressionKind::UnaryMessage { ref message, ref receiver } => ExpressionKind::UnaryMessage {
+                message: *message,
+                receiver: strip(receiver),
+            },
+            ref kind => kind.clone(),
+        };
+
+        From::from(kind)
+    }
+}
+
+impl From<ExpressionKind> for Expression {
+    fn from(kind: ExpressionKind) -> Expression {
+        Expression::new(kind, Span::default())
+    }
+}
+
+impl Method {
+    pub fn name(&self) -> Name {
+        match *self {
+            Method::Primitive { ref name, .. } | Method::Native { ref name, .. } => *name,
+        }
+    }
+
+    pub fn parameters(&self) -> &[Name] {
+        match *self {
+            Method::Primitive { ref parameters, .. } => parameters,
+            Method::Native { ref body, .. } => &body.parameters,
+        }
+    }
+
+    pub fn parameter_spans(&self) -> &[Span] {
+        match *self {
+            Method::Primitive { ref parameter_spans, .. } => parameter_spans,
+            Method::Native { ref body, .. } => &body.parameter_spans,
+        }
+    }
+
+    pub fn comments(&self) -> &[String] {
+        match *self {
+            Method::Primitive { ref comments, .. } | Method::Native { ref comments, .. } => comments,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match *self {
+            Method::Primitive { span, .. } | Method::Native { span, .. } => span,
+        }
+    }
+
+    pub fn without_spans(&self) -> Method {
+        match *self {
+            Method::Primitive { ref name, ref parameters, ref comments, .. } => Method::Primitive {
+                name: *name,
+                parameters: parameters.clone(),
+                parameter_spans: vec![Span::default(); parameters.len()],
+                comments: comments.clone(),
+                span: Span::default(),
+            },
+            Method::Native { ref name, ref body, ref comments, .. } => Method::Native {
+                name: *name,
+                body: body.without_spans(),
+                comments: comments.clone(),
+                span: Span::default(),
+            },
+        }
+    }
+}
+
+impl Superclass {
+    pub fn name(&self, interner: &mut Interner) -> Option<Name> {
+        match *self {
+            Superclass::None => None,
+            Superclass::Implicit => Some(interner.intern("Object")),
+            Superclass::Named(name) => Some(name),
+        }
+    }
+}
+
+impl MethodTable {
+    pub fn new() -> MethodTable {
+        Default::default()
+    }
+
+    pub fn from_methods<I: IntoIterator<Item = Method>>(methods: I) -> Result<MethodTable, Vec<Method>> {
+        let mut table = MethodTable::new();
+        table.extend(methods).map(|()| table)
+    }
+
+    fn extend<I: IntoIterator<Item = Method>>(&mut self, methods: I) -> Result<(), Vec<Method>> {
+        let duplicates: Vec<_> = methods.into_iter().filter_map(|method| self.insert(method).err()).map(|method| *method).collect();
+        if duplicates.is_empty() {
+            Ok(())
+        } else {
+            Err(duplicates)
+        }
+    }
+
+    pub fn insert(&mut self, method: Method) -> Result<(), Box<Method>> {
+        let name = method.name();
+        if self.index.contains_key(&name) {
+            return Err(Box::new(method));
+        }
+
+        self.index.insert(name, self.methods.len());
+        self.methods.push(method);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &Name) -> Option<&Method> {
+        self.index.get(name).map(|&position| &self.methods[position])
+    }
+
+    pub fn contains_key(&self, name: &Name) -> bool {
+        self.index.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = Name> + '_ {
+        self.methods.iter().map(Method::name)
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Method> {
+        self.methods.iter()
+    }
+
+    pub fn for_each_mut<F: FnMut(&mut Method)>(&mut self, mut f: F) -> Result<(), Vec<Method>> {
+        self.index.clear();
+        let methods = mem::take(&mut self.methods);
+        self.extend(methods.into_iter().map(|mut method| {
+            f(&mut method);
+            method
+        }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.methods.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.methods.is_empty()
+    }
+}
+
+impl fmt::Debug for MethodTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.methods.iter().map(|method| (method.name(), method))).finish()
+    }
+}
+
+impl Index<&Name> for MethodTable {
+    type Output = Method;
+
+    fn index(&self, name: &Name) -> &Method {
+        self.get(name).expect("no method with that name")
+    }
+}
+
+impl<'a> IntoIterator for &'a MethodTable {
+    type Item = &'a Method;
+    type IntoIter = slice::Iter<'a, Method>;
+
+    fn into_iter(self) -> slice::Iter<'a, Method> {
+        self.methods.iter()
+    }
+}
+
+impl IntoIterator for MethodTable {
+    type Item = Method;
+    type IntoIter = vec::IntoIter<Method>;
+
+    fn into_iter(self) -> vec::IntoIter<Method> {
+        self.methods.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Method, MethodTable, Name};
+    use compiler::interner::Interner;
+    use compiler::test_util::parse;
+
+    fn method(source: &str) -> (Method, Interner) {
+        let (class, interner) = parse(&format!("Test = ( {} )", source));
+        (class.instance_methods.iter().next().unwrap().clone(), interner)
+    }
+
+    fn names(interner: &Interner, names: &[&str]) -> Vec<Name> {
+        names.iter().map(|&name| interner.get(name).unwrap()).collect()
+    }
+
+    #[test]
+    fn method_body_is_a_block() {
+        let (method, interner) = method("at: i put: v = ( | old | old := i. ^ old )");
+        assert_eq!(method.parameters(), &names(&interner, &["i", "v"])[..]);
+        match method {
+            Method::Native { ref body, .. } => {
+                assert_eq!(body.locals, names(&interner, &["old"]));
+                assert_eq!(body.body.len(), 2);
+                assert_eq!(format!("{:?}", body.span), "1:25..1:52");
+            }
+            ref method => panic!("unexpected method {:?}", method),
+        }
+    }
+
+    #[test]
+    fn free_variables_skip_bound_names() {
+        let (method, interner) = method("run: x = ( | t | t := x + y. ^ [ :a | | b | b := a + t + z. [ a + w ] ] )");
+        match method {
+            Method::Native { ref body, .. } => {
+                assert_eq!(body.free_variables(), names(&interner, &["y", "z", "w"]));
+                assert_eq!(body.captured_variables(), names(&interner, &["t"]));
+            }
+            ref method => panic!("unexpected method {:?}", method),
+        }
+    }
+
+    #[test]
+    fn shadowed_variables_are_not_captured() {
+        let (method, interner) = method("run: x = ( | t | ^ [ :t | [ t + x ] ] )");
+        match method {
+            Method::Native { ref body, .. } => {
+                assert_eq!(body.free_variables(), names(&interner, &[]));
+                assert_eq!(body.captured_variables(), names(&interner, &["x"]));
+            }
+            ref method => panic!("unexpected method {:?}", method),
+        }
+    }
+
+    #[test]
+    fn method_table_keeps_first_duplicate() {
+        let (first, _) = method("run = ( ^ 1 )");
+        let (second, _) = method("run = ( ^ 2 )");
+        let mut table = MethodTable::new();
+        assert!(table.insert(first.clone()).is_ok());
+        assert_eq!(table.insert(second.clone()), Err(Box::new(second.clone())));
+        assert_eq!(table.iter().collect::<Vec<_>>(), vec![&first]);
+        assert_eq!(MethodTable::from_methods(vec![first, second.clone()]).err(), Some(vec![second]));
+    }
 }
@@ -0,0 +1,180 @@
+use compiler::ast::{Block, Class, Expression, ExpressionKind, Location, Method, Name, Span};
+use compiler::grammar::is_reserved_name;
+use compiler::interner::Interner;
+use compiler::visit::{walk_block, walk_expression, Visitor};
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Diagnostic {
+    ArityMismatchError { selector: Name, expected: usize, found: usize, location: Location },
+    DuplicateParameterError { name: Name, location: Location },
+    ReservedAssignmentError { name: Name, location: Location },
+    InitializerReturnError { location: Location },
+}
+
+impl Diagnostic {
+    pub fn location(&self) -> Location {
+        match *self {
+            Diagnostic::ArityMismatchError { location, .. } |
+            Diagnostic::DuplicateParameterError { location, .. } |
+            Diagnostic::ReservedAssignmentError { location, .. } |
+            Diagnostic::InitializerReturnError { location } => location,
+        }
+    }
+}
+
+fn arity(selector: &str) -> usize {
+    if selector.ends_with(':') {
+        selector.matches(':').count()
+    } else if selector.starts_with(|c: char| c.is_alphabetic()) {
+        0
+    } else {
+        1
+    }
+}
+
+struct Checker<'a> {
+    interner: &'a Interner,
+    diagnostics: Vec<Diagnostic>,
+    initializer: bool,
+}
+
+impl<'a> Checker<'a> {
+    fn check_parameters(&mut self, parameters: &[Name], spans: &[Span]) {
+        for (index, (&name, span)) in parameters.iter().zip(spans).enumerate() {
+            if parameters[..index].contains(&name) {
+                self.diagnostics.push(Diagnostic::DuplicateParameterError { name, location: span.start });
+            }
+        }
+    }
+}
+
+impl<'a> Visitor for Checker<'a> {
+    fn visit_method(&mut self, method: &Method) {
+        let location = method.span().start;
+        let expected = arity(self.interner.resolve(method.name()));
+        let found = method.parameters().len();
+        if expected != found {
+            self.diagnostics.push(Diagnostic::ArityMismatchError { selector: method.name(), expected, found, location });
+        }
+
+        self.check_parameters(method.parameters(), method.parameter_spans());
+        if let Method::Native { ref body, .. } = *method {
+            walk_block(self, body);
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.check_parameters(&block.parameters, &block.parameter_spans);
+        walk_block(self, block);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        let location = expression.span.start;
+        match expression.kind {
+            ExpressionKind::Assignment { ref variables, ref variable_spans, .. } => {
+                let interner = self.interner;
+                for (&name, span) in variables.iter().zip(variable_spans).filter(|&(&name, _)| is_reserved_name(interner.resolve(name))) {
+                    self.diagnostics.push(Diagnostic::ReservedAssignmentError { name, location: span.start });
+                }
+            }
+            ExpressionKind::Return(_) if self.initializer => {
+                self.diagnostics.push(Diagnostic::InitializerReturnError { location });
+            }
+            _ => {}
+        }
+
+        walk_expression(self, expression);
+    }
+}
+
+pub fn check(class: &Class, interner: &Interner) -> Vec<Diagnostic> {
+    let mut checker = Checker { interner, diagnostics: vec![], initializer: false };
+    for method in &class.instance_methods {
+        checker.visit_method(method);
+    }
+
+    for method in &class.class_methods {
+        checker.initializer = interner.resolve(method.name()) == "initialize";
+        checker.visit_method(method);
+    }
+
+    checker.diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, Diagnostic};
+    use compiler::ast::{Block, Location, Method, Span};
+    use compiler::test_util::parse;
+
+    #[test]
+    fn accepts_valid_class() {
+        let source = "Test = ( at: i put: v = ( ^ [ :a :b | a + b ] ) + other = primitive ---- initialize = ( self foo ) new = ( ^ self ) )";
+        let (class, interner) = parse(source);
+        assert_eq!(check(&class, &interner), vec![]);
+    }
+
+    #[test]
+    fn reports_arity_mismatches() {
+        let source = "Test = ( run = ( ) )";
+        let (mut class, mut interner) = parse(source);
+        let location = Location(1, 10, 9);
+        let span = Span::new(location, location);
+        let (at_put, plus, size) = (interner.intern("at:put:"), interner.intern("+"), interner.intern("size"));
+        class.instance_methods.insert(Method::Native {
+            name: at_put,
+            body: Block::new(vec![interner.intern("i")], vec![span], vec![], vec![], span),
+            comments: vec![],
+            span,
+        }).unwrap();
+        class.instance_methods.insert(Method::Primitive { name: plus, parameters: vec![], parameter_spans: vec![], comments: vec![], span }).unwrap();
+        let x = interner.intern("x");
+        class.instance_methods.insert(Method::Primitive { name: size, parameters: vec![x], parameter_spans: vec![span], comments: vec![], span }).unwrap();
+
+        assert_eq!(check(&class, &interner), vec![
+            Diagnostic::ArityMismatchError { selector: at_put, expected: 2, found: 1, location },
+            Diagnostic::ArityMismatchError { selector: plus, expected: 1, found: 0, location },
+            Diagnostic::ArityMismatchError { selector: size, expected: 0, found: 1, location },
+        ]);
+    }
+
+    #[test]
+    fn reports_duplicate_parameters() {
+        let source = "Test = ( at: i put: i = ( ^ [ :a :b :a | a ] ) )";
+        let (class, interner) = parse(source);
+        assert_eq!(check(&class, &interner), vec![
+            Diagnostic::DuplicateParameterError { name: interner.get("i").unwrap(), location: Location(1, 21, 20) },
+            Diagnostic::DuplicateParameterError { name: interner.get("a").unwrap(), location: Location(1, 38, 37) },
+        ]);
+
+        let source = "Test = (\n    at: x\n    put: x = primitive )";
+        let (class, interner) = parse(source);
+        assert_eq!(check(&class, &interner), vec![
+            Diagnostic::DuplicateParameterError { name: interner.get("x").unwrap(), location: Location(3, 10, 28) },
+        ]);
+    }
+
+    #[test]
+    fn reports_assignments_to_reserved_names() {
+        let source = "Test = ( run = ( self := 1. [ nil := true := 2 ] ) )";
+        let (class, interner) = parse(source);
+        assert_eq!(check(&class, &interner), vec![
+            Diagnostic::ReservedAssignmentError { name: interner.get("self").unwrap(), location: Location(1, 18, 17) },
+            Diagnostic::ReservedAssignmentError { name: interner.get("nil").unwrap(), location: Location(1, 31, 30) },
+            Diagnostic::ReservedAssignmentError { name: interner.get("true").unwrap(), location: Location(1, 38, 37) },
+        ]);
+    }
+
+    #[test]
+    fn reports_returns_in_class_side_initializer() {
+        let source = "Test = ( initialize = ( ^ self ) ---- initialize = ( x do: [ ^ 1 ]. ^ self ) )";
+        let (class, interner) = parse(source);
+        let diagnostics = check(&class, &interner);
+        assert_eq!(diagnostics, vec![
+            Diagnostic::InitializerReturnError { location: Location(1, 62, 61) },
+            Diagnostic::InitializerReturnError { location: Location(1, 69, 68) },
+        ]);
+        assert_eq!(diagnostics[0].location(), Location(1, 62, 61));
+    }
+}
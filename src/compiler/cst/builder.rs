@@ -0,0 +1,452 @@
+use compiler::cst::{GreenElement, GreenNode, GreenToken, SyntaxKind};
+use compiler::lexer::BorrowedItem;
+use compiler::grammar::{is_binary_operator, is_literal, is_pattern_start, is_trivia};
+use compiler::{Error, ParserOptions, SourceLexer, Symbol};
+
+pub struct Builder<'a> {
+    source: &'a str,
+    options: ParserOptions,
+    items: Vec<BorrowedItem<'a>>,
+    significant: Vec<usize>,
+    position: usize,
+    cursor: usize,
+    stack: Vec<(SyntaxKind, Vec<GreenElement>)>,
+}
+
+impl<'a> Builder<'a> {
+    pub fn new(source: &'a str, options: ParserOptions) -> Result<Builder<'a>, Error> {
+        let items = SourceLexer::new(source)
+            .with_trivia()
+            .with_options(options.clone())
+            .collect::<Result<Vec<_>, _>>()?;
+        let significant = (0..items.len()).filter(|&index| !is_trivia(&(items[index].0).0)).collect();
+
+        Ok(Builder {
+            source,
+            options,
+            items,
+            significant,
+            position: 0,
+            cursor: 0,
+            stack: vec![(SyntaxKind::Root, vec![])],
+        })
+    }
+
+    pub fn build(mut self) -> Result<GreenNode, Error> {
+        self.class()?;
+        if self.cursor < self.significant.len() {
+            return Err(self.unexpected_token());
+        }
+
+        self.flush_trivia();
+        let (kind, children) = self.stack.pop().unwrap();
+        Ok(GreenNode::new(kind, children))
+    }
+
+    fn class(&mut self) -> Result<(), Error> {
+        self.start_node(SyntaxKind::Class);
+        self.expect(Symbol::Identifier)?;
+        self.expect(Symbol::Equal)?;
+        if self.at(Symbol::Identifier) {
+            self.bump();
+        }
+
+        self.expect(Symbol::NewTerm)?;
+        self.locals()?;
+        self.methods()?;
+        if self.at(Symbol::Separator) {
+            self.bump();
+            self.locals()?;
+            self.methods()?;
+        }
+
+        self.expect(Symbol::EndTerm)?;
+        self.finish_node();
+        Ok(())
+    }
+
+    fn methods(&mut self) -> Result<(), Error> {
+        while self.peek(1).is_some_and(is_pattern_start) {
+            self.method()?;
+        }
+
+        Ok(())
+    }
+
+    fn method(&mut self) -> Result<(), Error> {
+        self.start_node(SyntaxKind::Method);
+        match self.peek(1) {
+            Some(&Symbol::Identifier) => self.bump(),
+            Some(&Symbol::Keyword) => {
+                while self.at(Symbol::Keyword) {
+                    self.bump();
+                    self.expect(Symbol::Identifier)?;
+                }
+            }
+            Some(symbol) if is_binary_operator(symbol) => {
+                self.bump();
+                self.expect(Symbol::Identifier)?;
+            }
+            _ => return Err(self.unexpected_token()),
+        }
+
+        self.expect(Symbol::Equal)?;
+        if self.at(Symbol::Primitive) {
+            self.bump();
+        } else {
+            self.start_node(SyntaxKind::Body);
+            self.expect(Symbol::NewTerm)?;
+            self.locals()?;
+            self.statements()?;
+            self.expect(Symbol::EndTerm)?;
+            self.finish_node();
+        }
+
+        self.finish_node();
+        Ok(())
+    }
+
+    fn locals(&mut self) -> Result<(), Error> {
+        if self.at(Symbol::Or) {
+            self.start_node(SyntaxKind::Locals);
+            self.bump();
+            while self.at(Symbol::Identifier) {
+                self.bump();
+            }
+
+            self.expect(Symbol::Or)?;
+            self.finish_node();
+        }
+
+        Ok(())
+    }
+
+    fn block_parameters(&mut self) -> Result<(), Error> {
+        if self.at(Symbol::Colon) {
+            self.start_node(SyntaxKind::BlockParameters);
+            while self.at(Symbol::Colon) {
+                self.bump();
+                self.expect(Symbol::Identifier)?;
+            }
+
+            self.expect(Symbol::Or)?;
+            self.finish_node();
+        }
+
+        Ok(())
+    }
+
+    fn statements(&mut self) -> Result<(), Error> {
+        loop {
+            match self.peek(1) {
+                Some(&Symbol::EndTerm) | Some(&Symbol::EndBlock) | None => break,
+                Some(&Symbol::Exit) => {
+                    self.start_node(SyntaxKind::Return);
+                    self.bump();
+                    self.expression()?;
+                    self.finish_node();
+                }
+                Some(_) => self.expression()?,
+            }
+
+            if !self.at(Symbol::Period) {
+                break;
+            }
+
+            self.bump();
+        }
+
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<(), Error> {
+        if self.peek(2) == Some(&Symbol::Assign) {
+            self.start_node(SyntaxKind::Assignment);
+            while self.peek(2) == Some(&Symbol::Assign) {
+                self.expect(Symbol::Identifier)?;
+                self.bump();
+            }
+
+            self.expression()?;
+            self.finish_node();
+            return Ok(());
+        }
+
+        let checkpoint = self.checkpoint();
+        self.primary()?;
+        loop {
+            match self.peek(1) {
+                Some(&Symbol::Identifier) => {
+                    while self.at(Symbol::Identifier) {
+                        self.unary_message(checkpoint)?;
+                    }
+                }
+                Some(&Symbol::Keyword) => self.keyword_message(checkpoint)?,
+                Some(symbol) if is_binary_operator(symbol) => self.binary_message(checkpoint)?,
+                Some(&Symbol::Semicolon) if self.options.cascades => return self.cascade(checkpoint),
+                _ => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cascade(&mut self, checkpoint: usize) -> Result<(), Error> {
+        let is_message = match self.stack.last().unwrap().1.last() {
+            Some(GreenElement::Node(node)) => matches!(
+                node.kind(),
+                SyntaxKind::UnaryMessage | SyntaxKind::BinaryMessage | SyntaxKind::KeywordMessage
+            ),
+            _ => false,
+        };
+
+        if !is_message {
+            return Err(self.unexpected_token());
+        }
+
+        self.start_node_at(checkpoint, SyntaxKind::Cascade);
+        while self.at(Symbol::Semicolon) {
+            self.bump();
+            self.start_node(SyntaxKind::CascadeMessage);
+            match self.peek(1) {
+                Some(&Symbol::Identifier) => self.bump(),
+                Some(&Symbol::Keyword) => {
+                    while self.at(Symbol::Keyword) {
+                        self.bump();
+                        self.formula()?;
+                    }
+                }
+                Some(symbol) if is_binary_operator(symbol) => {
+                    self.bump();
+                    self.binary_operand()?;
+                }
+                _ => return Err(self.unexpected_token()),
+            }
+
+            self.finish_node();
+        }
+
+        self.finish_node();
+        Ok(())
+    }
+
+    fn unary_message(&mut self, checkpoint: usize) -> Result<(), Error> {
+        self.start_node_at(checkpoint, SyntaxKind::UnaryMessage);
+        self.expect(Symbol::Identifier)?;
+        self.finish_node();
+        Ok(())
+    }
+
+    fn keyword_message(&mut self, checkpoint: usize) -> Result<(), Error> {
+        self.start_node_at(checkpoint, SyntaxKind::KeywordMessage);
+        while self.at(Symbol::Keyword) {
+            self.bump();
+            self.formula()?;
+        }
+
+        self.finish_node();
+        Ok(())
+    }
+
+    fn formula(&mut self) -> Result<(), Error> {
+        let checkpoint = self.checkpoint();
+        self.binary_operand()?;
+        while self.peek(1).is_some_and(is_binary_operator) {
+            self.binary_message(checkpoint)?;
+        }
+
+        Ok(())
+    }
+
+    fn binary_operand(&mut self) -> Result<(), Error> {
+        let checkpoint = self.checkpoint();
+        self.primary()?;
+        while self.at(Symbol::Identifier) {
+            self.unary_message(checkpoint)?;
+        }
+
+        Ok(())
+    }
+
+    fn binary_message(&mut self, checkpoint: usize) -> Result<(), Error> {
+        self.start_node_at(checkpoint, SyntaxKind::BinaryMessage);
+        self.bump();
+        self.binary_operand()?;
+        self.finish_node();
+        Ok(())
+    }
+
+    fn primary(&mut self) -> Result<(), Error> {
+        match self.peek(1) {
+            Some(&Symbol::Identifier) => self.leaf(SyntaxKind::Variable),
+            Some(symbol) if is_literal(symbol) => self.leaf(SyntaxKind::Literal),
+            Some(&Symbol::Pound) => return self.symbol(),
+            Some(&Symbol::NewBlock) => return self.block(),
+            Some(&Symbol::NewTerm) => {
+                self.start_node(SyntaxKind::Parenthesized);
+                self.bump();
+                self.expression()?;
+                self.expect(Symbol::EndTerm)?;
+                self.finish_node();
+            }
+            _ => return Err(self.unexpected_token()),
+        }
+
+        Ok(())
+    }
+
+    fn block(&mut self) -> Result<(), Error> {
+        self.start_node(SyntaxKind::Block);
+        self.bump();
+        self.block_parameters()?;
+        self.locals()?;
+        self.statements()?;
+        self.expect(Symbol::EndBlock)?;
+        self.finish_node();
+        Ok(())
+    }
+
+    fn symbol(&mut self) -> Result<(), Error> {
+        if self.peek(2) == Some(&Symbol::NewTerm) {
+            self.start_node(SyntaxKind::LiteralArray);
+            self.bump();
+            return self.literal_array();
+        }
+
+        self.start_node(SyntaxKind::Symbol);
+        self.bump();
+        match self.peek(1) {
+            Some(&Symbol::Keyword) => self.keyword_symbol(),
+            Some(&Symbol::Identifier) | Some(&Symbol::String) | Some(&Symbol::KeywordSequence) => self.bump(),
+            Some(symbol) if is_binary_operator(symbol) => self.bump(),
+            _ => return Err(self.unexpected_token()),
+        }
+
+        self.finish_node();
+        Ok(())
+    }
+
+    fn keyword_symbol(&mut self) {
+        self.bump();
+        while self.at(Symbol::Keyword) && self.adjacent() {
+            self.bump();
+        }
+    }
+
+    fn literal_array(&mut self) -> Result<(), Error> {
+        self.expect(Symbol::NewTerm)?;
+        loop {
+            match self.peek(1) {
+                Some(&Symbol::EndTerm) => break,
+                Some(symbol) if is_literal(symbol) => self.leaf(SyntaxKind::Literal),
+                Some(&Symbol::Pound) => self.symbol()?,
+                Some(&Symbol::NewTerm) => {
+                    self.start_node(SyntaxKind::LiteralArray);
+                    self.literal_array()?;
+                }
+                Some(&Symbol::Identifier) => self.leaf(SyntaxKind::Variable),
+                Some(&Symbol::Keyword) => {
+                    self.start_node(SyntaxKind::Symbol);
+                    self.keyword_symbol();
+                    self.finish_node();
+                }
+                Some(symbol) if *symbol == Symbol::KeywordSequence || is_binary_operator(symbol) => {
+                    self.leaf(SyntaxKind::Symbol)
+                }
+                _ => return Err(self.unexpected_token()),
+            }
+        }
+
+        self.bump();
+        self.finish_node();
+        Ok(())
+    }
+
+    fn leaf(&mut self, kind: SyntaxKind) {
+        self.start_node(kind);
+        self.bump();
+        self.finish_node();
+    }
+
+    fn peek(&self, n: usize) -> Option<&Symbol> {
+        self.significant.get(self.cursor + n - 1).map(|&index| &(self.items[index].0).0)
+    }
+
+    fn at(&self, symbol: Symbol) -> bool {
+        self.peek(1) == Some(&symbol)
+    }
+
+    fn adjacent(&self) -> bool {
+        let previous = &self.items[self.significant[self.cursor - 1]];
+        let next = &self.items[self.significant[self.cursor]];
+        previous.1.end == next.1.start
+    }
+
+    fn expect(&mut self, symbol: Symbol) -> Result<(), Error> {
+        match self.significant.get(self.cursor).map(|&index| &self.items[index]) {
+            Some(BorrowedItem(token, _)) if token.0 == symbol => {
+                self.bump();
+                Ok(())
+            }
+            Some(&BorrowedItem(ref token, span)) => Err(Error::MismatchError {
+                expected: vec![symbol],
+                found: token.0.clone(),
+                location: span.start,
+            }),
+            None => Err(Error::End),
+        }
+    }
+
+    fn unexpected_token(&self) -> Error {
+        match self.significant.get(self.cursor).map(|&index| &self.items[index]) {
+            Some(&BorrowedItem(ref token, span)) => Error::UnexpectedTokenError {
+                text: token.clone().into_owned().text(),
+                location: span.start,
+            },
+            None => Error::End,
+        }
+    }
+
+    fn flush_trivia(&mut self) {
+        let end = self.significant.get(self.cursor).cloned().unwrap_or(self.items.len());
+        while self.position < end {
+            let token = self.token(self.position);
+            self.stack.last_mut().unwrap().1.push(token);
+            self.position += 1;
+        }
+    }
+
+    fn token(&self, index: usize) -> GreenElement {
+        let BorrowedItem(ref token, span) = self.items[index];
+        let text = &self.source[span.start.offset()..span.end.offset()];
+        GreenToken::new(token.clone().into_owned(), text).into()
+    }
+
+    fn bump(&mut self) {
+        self.flush_trivia();
+        let token = self.token(self.position);
+        self.stack.last_mut().unwrap().1.push(token);
+        self.position += 1;
+        self.cursor += 1;
+    }
+
+    fn checkpoint(&mut self) -> usize {
+        self.flush_trivia();
+        self.stack.last().unwrap().1.len()
+    }
+
+    fn start_node(&mut self, kind: SyntaxKind) {
+        self.flush_trivia();
+        self.stack.push((kind, vec![]));
+    }
+
+    fn start_node_at(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        let children = self.stack.last_mut().unwrap().1.split_off(checkpoint);
+        self.stack.push((kind, children));
+    }
+
+    fn finish_node(&mut self) {
+        let (kind, children) = self.stack.pop().unwrap();
+        self.stack.last_mut().unwrap().1.push(GreenNode::new(kind, children).into());
+    }
+}
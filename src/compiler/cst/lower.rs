@@ -0,0 +1,429 @@
+use compiler::ast::{self, Expression, ExpressionKind, Name, Span};
+use compiler::cst::{GreenElement, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+use compiler::grammar::{is_binary_operator, is_reserved_name, number_literal};
+use compiler::interner::Interner;
+use compiler::{Error, ParserOptions, Symbol};
+
+pub fn lower_root(root: &SyntaxNode, options: &ParserOptions, interner: &mut Interner) -> Result<ast::Class, Error> {
+    if root.kind() != SyntaxKind::Root {
+        return Err(malformed(root));
+    }
+
+    if let Some(token) = root.child_tokens().first() {
+        return Err(unexpected(token));
+    }
+
+    match root.child_nodes().as_slice() {
+        [] => Err(Error::End),
+        [class] if class.kind() == SyntaxKind::Class => lower_class(class, options, interner),
+        [_, node, ..] | [node] => Err(malformed(node)),
+    }
+}
+
+fn malformed(node: &SyntaxNode) -> Error {
+    Error::UnexpectedTokenError { text: node.text(), location: node.span().start }
+}
+
+fn unexpected(token: &SyntaxToken) -> Error {
+    Error::UnexpectedTokenError { text: token.text().to_string(), location: token.span().start }
+}
+
+fn expect(token: Option<&SyntaxToken>, symbol: Symbol, node: &SyntaxNode) -> Result<(), Error> {
+    match token {
+        Some(token) if *token.symbol() == symbol => Ok(()),
+        Some(token) => Err(unexpected(token)),
+        None => Err(malformed(node)),
+    }
+}
+
+fn expect_name(token: Option<&SyntaxToken>, node: &SyntaxNode, interner: &mut Interner) -> Result<Name, Error> {
+    expect(token, Symbol::Identifier, node)?;
+    Ok(interner.intern(&token.map(text).unwrap_or_default()))
+}
+
+fn delimited(node: &SyntaxNode, open: Symbol, close: Symbol, separator: Option<Symbol>) -> Result<Vec<SyntaxToken>, Error> {
+    let mut tokens = node.child_tokens();
+    expect(tokens.first(), open, node)?;
+    expect(tokens.last().filter(|_| tokens.len() > 1), close, node)?;
+    tokens.pop();
+    tokens.remove(0);
+    match tokens.iter().find(|token| Some(token.symbol()) != separator.as_ref()) {
+        Some(token) => Err(unexpected(token)),
+        None => Ok(tokens),
+    }
+}
+
+fn single_node(node: &SyntaxNode) -> Result<SyntaxNode, Error> {
+    match node.child_nodes().as_slice() {
+        [child] => Ok(child.clone()),
+        _ => Err(malformed(node)),
+    }
+}
+
+fn single_token(node: &SyntaxNode) -> Result<SyntaxToken, Error> {
+    match (node.child_tokens().as_slice(), node.child_nodes().is_empty()) {
+        ([token], true) => Ok(token.clone()),
+        _ => Err(malformed(node)),
+    }
+}
+
+fn leading_comments(node: &SyntaxNode) -> Vec<String> {
+    let (parent, index) = match node.0.parent {
+        Some((ref parent, index)) => (parent, index),
+        None => return vec![],
+    };
+
+    let mut comments = vec![];
+    for child in parent.green().children()[..index].iter().rev() {
+        match *child {
+            GreenElement::Token(ref token) if token.is_trivia() => {
+                if *token.symbol() == Symbol::Comment {
+                    let text = token.text();
+                    comments.push(text[1..text.len() - 1].to_string());
+                }
+            }
+            _ => break,
+        }
+    }
+
+    comments.reverse();
+    comments
+}
+
+fn text(token: &SyntaxToken) -> String {
+    token.token().text()
+}
+
+fn lower_class(node: &SyntaxNode, options: &ParserOptions, interner: &mut Interner) -> Result<ast::Class, Error> {
+    let tokens = node.child_tokens();
+    let name = expect_name(tokens.first(), node, interner)?;
+    expect(tokens.get(1), Symbol::Equal, node)?;
+    let (superclass, body) = match tokens.get(2) {
+        Some(token) if *token.symbol() == Symbol::Identifier && text(token) == "nil" => (ast::Superclass::None, 4),
+        Some(token) if *token.symbol() == Symbol::Identifier => (ast::Superclass::Named(interner.intern(&text(token))), 4),
+        _ => (options.default_superclass.clone(), 3),
+    };
+
+    expect(tokens.get(body - 1), Symbol::NewTerm, node)?;
+    expect(tokens.last().filter(|_| tokens.len() > body), Symbol::EndTerm, node)?;
+    match tokens[body..tokens.len() - 1] {
+        [] => {}
+        [ref token] if *token.symbol() == Symbol::Separator => {}
+        [ref token, ..] => return Err(unexpected(token)),
+    }
+
+    let mut instance_variables = vec![];
+    let mut instance_methods = ast::MethodTable::new();
+    let mut class_variables = vec![];
+    let mut class_methods = ast::MethodTable::new();
+    let mut class_side = false;
+    for child in node.children() {
+        match child {
+            SyntaxElement::Token(ref token) if *token.symbol() == Symbol::Separator => class_side = true,
+            SyntaxElement::Node(ref child) if child.kind() == SyntaxKind::Locals => {
+                let variables = lower_locals(child, interner)?;
+                if class_side {
+                    class_variables = variables;
+                } else {
+                    instance_variables = variables;
+                }
+            }
+            SyntaxElement::Node(ref child) if child.kind() == SyntaxKind::Method => {
+                let method = lower_method(child, interner)?;
+                let methods = if class_side { &mut class_methods } else { &mut instance_methods };
+                if let Err(method) = methods.insert(method) {
+                    return Err(Error::DuplicateMethodError { name: method.name(), location: method.span().start });
+                }
+            }
+            SyntaxElement::Node(ref child) => return Err(malformed(child)),
+            SyntaxElement::Token(_) => {}
+        }
+    }
+
+    Ok(ast::Class {
+        name,
+        superclass,
+        instance_methods,
+        instance_variables,
+        class_methods,
+        class_variables,
+        comments: leading_comments(node),
+        span: node.span(),
+    })
+}
+
+fn lower_locals(node: &SyntaxNode, interner: &mut Interner) -> Result<Vec<Name>, Error> {
+    if let Some(child) = node.child_nodes().first() {
+        return Err(malformed(child));
+    }
+
+    let mut locals = vec![];
+    for token in delimited(node, Symbol::Or, Symbol::Or, Some(Symbol::Identifier))? {
+        let name = interner.intern(&text(&token));
+        let location = token.span().start;
+        if locals.contains(&name) {
+            return Err(Error::DuplicateVariableError { name, location });
+        }
+
+        if is_reserved_name(interner.resolve(name)) {
+            return Err(Error::ReservedNameError { name, location });
+        }
+
+        locals.push(name);
+    }
+
+    Ok(locals)
+}
+
+fn lower_method(node: &SyntaxNode, interner: &mut Interner) -> Result<ast::Method, Error> {
+    let tokens = node.child_tokens();
+    let (name, parameters, end) = match tokens.first() {
+        Some(token) if *token.symbol() == Symbol::Identifier => (text(token), vec![], 1),
+        Some(token) if *token.symbol() == Symbol::Keyword => {
+            let mut name = String::new();
+            let mut parameters = vec![];
+            let mut end = 0;
+            while tokens.get(end).is_some_and(|token| *token.symbol() == Symbol::Keyword) {
+                name.push_str(&text(&tokens[end]));
+                parameters.push(expect_name(tokens.get(end + 1), node, interner)?);
+                end += 2;
+            }
+
+            (name, parameters, end)
+        }
+        Some(token) if is_binary_operator(token.symbol()) => (text(token), vec![expect_name(tokens.get(1), node, interner)?], 2),
+        Some(token) => return Err(unexpected(token)),
+        None => return Err(malformed(node)),
+    };
+
+    expect(tokens.get(end), Symbol::Equal, node)?;
+    let parameter_spans = tokens[..end].iter().skip(1).step_by(2).map(SyntaxToken::span).collect();
+    let name = interner.intern(&name);
+    let comments = leading_comments(node);
+    let span = node.span();
+    match (&tokens[end + 1..], node.child_nodes().as_slice()) {
+        ([], [body]) if body.kind() == SyntaxKind::Body => {
+            Ok(ast::Method::Native { name, body: lower_block(body, parameters, parameter_spans, interner)?, comments, span })
+        }
+        ([token], []) if *token.symbol() == Symbol::Primitive => {
+            Ok(ast::Method::Primitive { name, parameters, parameter_spans, comments, span })
+        }
+        ([token, ..], _) => Err(unexpected(token)),
+        _ => Err(malformed(node)),
+    }
+}
+
+fn lower_block_parameters(node: &SyntaxNode, interner: &mut Interner) -> Result<(Vec<Name>, Vec<Span>), Error> {
+    let tokens = node.child_tokens();
+    expect(tokens.last(), Symbol::Or, node)?;
+    let parameters = tokens[..tokens.len() - 1].chunks(2)
+        .map(|pair| {
+            expect(pair.first(), Symbol::Colon, node)?;
+            expect_name(pair.get(1), node, interner)
+        })
+        .collect::<Result<_, _>>()?;
+    let spans = tokens[..tokens.len() - 1].iter().skip(1).step_by(2).map(SyntaxToken::span).collect();
+    Ok((parameters, spans))
+}
+
+fn lower_block(node: &SyntaxNode, mut parameters: Vec<Name>, mut parameter_spans: Vec<Span>, interner: &mut Interner) -> Result<ast::Block, Error> {
+    match node.kind() {
+        SyntaxKind::Body => delimited(node, Symbol::NewTerm, Symbol::EndTerm, Some(Symbol::Period))?,
+        SyntaxKind::Block => delimited(node, Symbol::NewBlock, Symbol::EndBlock, Some(Symbol::Period))?,
+        _ => return Err(malformed(node)),
+    };
+
+    let mut locals = vec![];
+    let mut body = vec![];
+    for (index, child) in node.child_nodes().iter().enumerate() {
+        match child.kind() {
+            SyntaxKind::BlockParameters if index == 0 && node.kind() == SyntaxKind::Block => {
+                let (names, spans) = lower_block_parameters(child, interner)?;
+                parameters = names;
+                parameter_spans = spans;
+            }
+            SyntaxKind::Locals if body.is_empty() && locals.is_empty() => locals = lower_locals(child, interner)?,
+            _ => body.push(lower_expression(child, interner)?),
+        }
+    }
+
+    Ok(ast::Block::new(parameters, parameter_spans, locals, body, node.span()))
+}
+
+fn lower_message(node: &SyntaxNode, arguments: &[SyntaxNode], interner: &mut Interner) -> Result<ast::Message, Error> {
+    let tokens = node.child_tokens();
+    let valid = match tokens.first().map(SyntaxToken::symbol) {
+        Some(&Symbol::Identifier) => tokens.len() == 1 && arguments.is_empty(),
+        Some(&Symbol::Keyword) => tokens.len() == arguments.len() && tokens.iter().all(|token| *token.symbol() == Symbol::Keyword),
+        Some(symbol) if is_binary_operator(symbol) => tokens.len() == 1 && arguments.len() == 1,
+        _ => false,
+    };
+
+    if !valid {
+        return Err(malformed(node));
+    }
+
+    let selector: String = tokens.iter().map(text).collect();
+    let arguments = arguments.iter().map(|argument| lower_expression(argument, interner)).collect::<Result<_, _>>()?;
+    Ok(ast::Message { selector: interner.intern(&selector), arguments })
+}
+
+fn message_kind(message: &ast::Message, interner: &Interner) -> SyntaxKind {
+    if message.arguments.is_empty() {
+        SyntaxKind::UnaryMessage
+    } else if interner.resolve(message.selector).ends_with(':') {
+        SyntaxKind::KeywordMessage
+    } else {
+        SyntaxKind::BinaryMessage
+    }
+}
+
+fn lower_expression(node: &SyntaxNode, interner: &mut Interner) -> Result<Expression, Error> {
+    let span = node.span();
+    let nodes = node.child_nodes();
+    let tokens = node.child_tokens();
+    let kind = match node.kind() {
+        SyntaxKind::Return => {
+            expect(tokens.first().filter(|_| tokens.len() == 1), Symbol::Exit, node)?;
+            ExpressionKind::Return(Box::new(lower_expression(&single_node(node)?, interner)?))
+        }
+        SyntaxKind::Assignment => {
+            if tokens.is_empty() {
+                return Err(malformed(node));
+            }
+
+            let variables = tokens.chunks(2)
+                .map(|pair| {
+                    expect(pair.get(1), Symbol::Assign, node)?;
+                    expect_name(pair.first(), node, interner)
+                })
+                .collect::<Result<_, _>>()?;
+            let variable_spans = tokens.iter().step_by(2).map(SyntaxToken::span).collect();
+            let value = Box::new(lower_expression(&single_node(node)?, interner)?);
+            ExpressionKind::Assignment { variables, variable_spans, value }
+        }
+        SyntaxKind::UnaryMessage | SyntaxKind::BinaryMessage | SyntaxKind::KeywordMessage => {
+            let (receiver, arguments) = nodes.split_first().ok_or_else(|| malformed(node))?;
+            let message = lower_message(node, arguments, interner)?;
+            if message_kind(&message, interner) != node.kind() {
+                return Err(malformed(node));
+            }
+
+            let receiver = Box::new(lower_expression(receiver, interner)?);
+            let ast::Message { selector, mut arguments } = message;
+            match node.kind() {
+                SyntaxKind::UnaryMessage => ExpressionKind::UnaryMessage { receiver, message: selector },
+                SyntaxKind::BinaryMessage => {
+                    ExpressionKind::BinaryMessage { message: selector, left: receiver, right: Box::new(arguments.remove(0)) }
+                }
+                _ => ExpressionKind::KeywordMessage { receiver, message: selector, parameters: arguments },
+            }
+        }
+        SyntaxKind::Cascade => {
+            let (first, rest) = nodes.split_first().ok_or_else(|| malformed(node))?;
+            if tokens.len() != rest.len() {
+                return Err(malformed(node));
+            }
+
+            if let Some(token) = tokens.iter().find(|token| *token.symbol() != Symbol::Semicolon) {
+                return Err(unexpected(token));
+            }
+
+            let inner = first.child_nodes();
+            let (receiver, arguments) = inner.split_first().ok_or_else(|| malformed(first))?;
+            let message = lower_message(first, arguments, interner)?;
+            if message_kind(&message, interner) != first.kind() {
+                return Err(malformed(first));
+            }
+
+            let mut messages = vec![message];
+            for message in rest {
+                if message.kind() != SyntaxKind::CascadeMessage {
+                    return Err(malformed(message));
+                }
+
+                messages.push(lower_message(message, &message.child_nodes(), interner)?);
+            }
+
+            ExpressionKind::Cascade { receiver: Box::new(lower_expression(receiver, interner)?), messages }
+        }
+        SyntaxKind::Parenthesized => {
+            delimited(node, Symbol::NewTerm, Symbol::EndTerm, None)?;
+            lower_expression(&single_node(node)?, interner)?.kind
+        }
+        SyntaxKind::Variable => {
+            let token = single_token(node)?;
+            if *token.symbol() != Symbol::Identifier {
+                return Err(unexpected(&token));
+            }
+
+            match text(&token).as_str() {
+                "nil" => ExpressionKind::LiteralNil,
+                "true" => ExpressionKind::LiteralBoolean(true),
+                "false" => ExpressionKind::LiteralBoolean(false),
+                name => ExpressionKind::Variable(interner.intern(name)),
+            }
+        }
+        SyntaxKind::Literal => {
+            let token = single_token(node)?;
+            match *token.symbol() {
+                Symbol::String => ExpressionKind::LiteralString(text(&token)),
+                Symbol::Character => ExpressionKind::LiteralCharacter(text(&token).chars().next().ok_or_else(|| unexpected(&token))?),
+                Symbol::Integer | Symbol::Double => lower_number(&token)?,
+                _ => return Err(unexpected(&token)),
+            }
+        }
+        SyntaxKind::Symbol => {
+            let parts = match tokens.split_first() {
+                Some((pound, parts)) if *pound.symbol() == Symbol::Pound => parts,
+                _ => &tokens[..],
+            };
+
+            let valid = nodes.is_empty() && match parts.first().map(SyntaxToken::symbol) {
+                Some(&Symbol::Keyword) => parts.iter().all(|token| *token.symbol() == Symbol::Keyword),
+                Some(&Symbol::Identifier) | Some(&Symbol::String) | Some(&Symbol::KeywordSequence) => parts.len() == 1,
+                Some(symbol) => is_binary_operator(symbol) && parts.len() == 1,
+                None => false,
+            };
+
+            if !valid {
+                return Err(malformed(node));
+            }
+
+            ExpressionKind::LiteralSymbol(interner.intern(&parts.iter().map(text).collect::<String>()))
+        }
+        SyntaxKind::LiteralArray => {
+            match tokens.first() {
+                Some(token) if *token.symbol() == Symbol::Pound => {
+                    expect(tokens.get(1), Symbol::NewTerm, node)?;
+                    expect(tokens.get(2).filter(|_| tokens.len() == 3), Symbol::EndTerm, node)?;
+                }
+                _ => {
+                    delimited(node, Symbol::NewTerm, Symbol::EndTerm, None)?;
+                }
+            }
+
+            ExpressionKind::LiteralArray(nodes.iter().map(|node| lower_array_element(node, interner)).collect::<Result<_, _>>()?)
+        }
+        SyntaxKind::Block => ExpressionKind::Block(lower_block(node, vec![], vec![], interner)?),
+        _ => return Err(malformed(node)),
+    };
+
+    Ok(Expression::new(kind, span))
+}
+
+fn lower_array_element(node: &SyntaxNode, interner: &mut Interner) -> Result<Expression, Error> {
+    match node.kind() {
+        SyntaxKind::Literal | SyntaxKind::Symbol | SyntaxKind::LiteralArray | SyntaxKind::Variable => {}
+        _ => return Err(malformed(node)),
+    }
+
+    match lower_expression(node, interner)? {
+        Expression { kind: ExpressionKind::Variable(name), span } => Ok(Expression::new(ExpressionKind::LiteralSymbol(name), span)),
+        value => Ok(value),
+    }
+}
+
+fn lower_number(token: &SyntaxToken) -> Result<ExpressionKind, Error> {
+    number_literal(token.token()).ok_or_else(|| Error::NumberError { text: text(token), location: token.span().start })
+}
@@ -0,0 +1,561 @@
+use compiler::grammar::is_trivia;
+use compiler::{ast, Error, ParserOptions, Symbol, Token};
+use compiler::interner::Interner;
+use util::location::{Location, Span};
+use std::fmt;
+use std::rc::Rc;
+
+mod builder;
+mod lower;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Root,
+    Class,
+    Locals,
+    Method,
+    Body,
+    Block,
+    BlockParameters,
+    Return,
+    Assignment,
+    UnaryMessage,
+    BinaryMessage,
+    KeywordMessage,
+    Cascade,
+    CascadeMessage,
+    Parenthesized,
+    Variable,
+    Literal,
+    Symbol,
+    LiteralArray,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Extent {
+    lines: usize,
+    columns: usize,
+    bytes: usize,
+}
+
+impl Extent {
+    fn of(text: &str) -> Extent {
+        match text.rfind('\n') {
+            Some(index) => Extent {
+                lines: text.matches('\n').count(),
+                columns: text[index + 1..].chars().count(),
+                bytes: text.len(),
+            },
+            None => Extent { lines: 0, columns: text.chars().count(), bytes: text.len() },
+        }
+    }
+
+    fn then(self, next: Extent) -> Extent {
+        Extent {
+            lines: self.lines + next.lines,
+            columns: if next.lines == 0 { self.columns + next.columns } else { next.columns },
+            bytes: self.bytes + next.bytes,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GreenToken {
+    token: Token,
+    text: String,
+    extent: Extent,
+}
+
+impl GreenToken {
+    pub fn new(token: Token, text: &str) -> GreenToken {
+        GreenToken { token, text: text.to_string(), extent: Extent::of(text) }
+    }
+
+    pub fn symbol(&self) -> &Symbol {
+        &self.token.0
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        is_trivia(&self.token.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn len(&self) -> usize {
+        self.extent().bytes
+    }
+
+    fn extent(&self) -> Extent {
+        match *self {
+            GreenElement::Node(ref node) => node.extent,
+            GreenElement::Token(ref token) => token.extent,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn write_text(&self, output: &mut String) {
+        match *self {
+            GreenElement::Node(ref node) => node.write_text(output),
+            GreenElement::Token(ref token) => output.push_str(token.text()),
+        }
+    }
+}
+
+impl From<GreenNode> for GreenElement {
+    fn from(node: GreenNode) -> GreenElement {
+        GreenElement::Node(Rc::new(node))
+    }
+}
+
+impl From<GreenToken> for GreenElement {
+    fn from(token: GreenToken) -> GreenElement {
+        GreenElement::Token(Rc::new(token))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    extent: Extent,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> GreenNode {
+        let extent = children.iter().fold(Extent::default(), |extent, child| extent.then(child.extent()));
+        GreenNode { kind, extent, children }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn len(&self) -> usize {
+        self.extent.bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    pub fn text(&self) -> String {
+        let mut output = String::with_capacity(self.len());
+        self.write_text(&mut output);
+        output
+    }
+
+    fn write_text(&self, output: &mut String) {
+        for child in &self.children {
+            child.write_text(output);
+        }
+    }
+}
+
+fn advance(Location(line, column, offset): Location, extent: Extent) -> Location {
+    if extent.lines == 0 {
+        Location(line, column + extent.columns, offset + extent.bytes)
+    } else {
+        Location(line + extent.lines, 1 + extent.columns, offset + extent.bytes)
+    }
+}
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    start: Location,
+    parent: Option<(SyntaxNode, usize)>,
+}
+
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    start: Location,
+    parent: SyntaxNode,
+    index: usize,
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData { green, start: Location(1, 1, 0), parent: None }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind()
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.as_ref().map(|(parent, _)| parent.clone())
+    }
+
+    pub fn text(&self) -> String {
+        self.0.green.text()
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.0.start, advance(self.0.start, self.0.green.extent))
+    }
+
+    pub fn children(&self) -> Vec<SyntaxElement> {
+        let mut start = self.0.start;
+        let mut children = vec![];
+        for (index, child) in self.0.green.children().iter().enumerate() {
+            children.push(match *child {
+                GreenElement::Node(ref green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    start,
+                    parent: Some((self.clone(), index)),
+                }))),
+                GreenElement::Token(ref green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    start,
+                    parent: self.clone(),
+                    index,
+                }),
+            });
+
+            start = advance(start, child.extent());
+        }
+
+        children
+    }
+
+    pub fn child_nodes(&self) -> Vec<SyntaxNode> {
+        self.children().into_iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }).collect()
+    }
+
+    pub fn child_tokens(&self) -> Vec<SyntaxToken> {
+        self.children().into_iter().filter_map(|child| match child {
+            SyntaxElement::Token(ref token) if token.green.is_trivia() => None,
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(_) => None,
+        }).collect()
+    }
+
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut nodes = vec![self.clone()];
+        for child in self.child_nodes() {
+            nodes.extend(child.descendants());
+        }
+
+        nodes
+    }
+
+    pub fn replace_with(&self, replacement: GreenNode) -> GreenNode {
+        match self.0.parent {
+            Some((ref parent, index)) => parent.replace_child(index, replacement.into()),
+            None => replacement,
+        }
+    }
+
+    fn replace_child(&self, index: usize, replacement: GreenElement) -> GreenNode {
+        let mut children = self.0.green.children().to_vec();
+        children[index] = replacement;
+        self.replace_with(GreenNode::new(self.kind(), children))
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}@{:?}", self.kind(), self.span())
+    }
+}
+
+impl SyntaxToken {
+    pub fn green(&self) -> &Rc<GreenToken> {
+        &self.green
+    }
+
+    pub fn symbol(&self) -> &Symbol {
+        self.green.symbol()
+    }
+
+    pub fn token(&self) -> &Token {
+        self.green.token()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.start, advance(self.start, self.green.extent))
+    }
+
+    pub fn replace_with(&self, replacement: GreenToken) -> GreenNode {
+        self.parent.replace_child(self.index, replacement.into())
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}@{:?} {:?}", self.symbol(), self.span(), self.text())
+    }
+}
+
+pub struct SyntaxTree {
+    green: Rc<GreenNode>,
+    options: ParserOptions,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str, options: ParserOptions) -> Result<SyntaxTree, Error> {
+        let green = builder::Builder::new(source, options.clone())?.build()?;
+        Ok(SyntaxTree { green: Rc::new(green), options })
+    }
+
+    pub fn from_green(green: GreenNode, options: ParserOptions) -> SyntaxTree {
+        SyntaxTree { green: Rc::new(green), options }
+    }
+
+    pub fn root(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green.clone())
+    }
+
+    pub fn text(&self) -> String {
+        self.green.text()
+    }
+
+    pub fn lower(&self, interner: &mut Interner) -> Result<ast::Class, Error> {
+        lower::lower_root(&self.root(), &self.options, interner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GreenNode, GreenToken, SyntaxKind, SyntaxTree};
+    use compiler::interner::Interner;
+    use compiler::{Dialect, Error, Parser, ParserOptions, Symbol, Token};
+    use quickcheck::{Arbitrary, Gen, QuickCheck};
+    use util::location::Location;
+
+    const SOURCE: &str = "\"Shapes\" Square = Shape (
+    | side |
+
+    \"Area of the square\"
+    area = ( ^ (side * side) ) \"trailing\"
+    side: aNumber = ( side := aNumber. ^ self )
+    ----
+    new: side = ( ^ self new side: side; yourself )
+)
+";
+
+    const FRAGMENTS: [&str; 40] = [
+        "Test", "Object", "run", "x", "nil", "self", "super", "primitive", "at:", "put:", "a:b:", "=", ":=", "(", ")", "[", "]",
+        "|", ":", "^", ".", ";", "+", "-", "--", "----", "==>", "#", "#(", "#at:put:", "#+", "#'a b'", "1", "-1", "1.5", "1e400",
+        "100000000000000000000", "'s'", "$a", "\"c\"",
+    ];
+
+    #[derive(Clone, Debug)]
+    struct ArbitraryDialect(Dialect);
+
+    impl Arbitrary for ArbitraryDialect {
+        fn arbitrary(g: &mut Gen) -> ArbitraryDialect {
+            ArbitraryDialect(*g.choose(&[Dialect::SomJava, Dialect::SomCpp, Dialect::TruffleSom, Dialect::SomNsLite]).unwrap())
+        }
+    }
+
+    fn options() -> ParserOptions {
+        ParserOptions { cascades: true, ..ParserOptions::default() }
+    }
+
+    fn parse(source: &str) -> SyntaxTree {
+        SyntaxTree::parse(source, options()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_source_text() {
+        let tree = parse(SOURCE);
+        assert_eq!(tree.text(), SOURCE);
+        assert_eq!(tree.root().span().end, Location(10, 1, SOURCE.len()));
+    }
+
+    #[test]
+    fn keeps_punctuation_tokens() {
+        let tree = parse(SOURCE);
+        let symbols: Vec<_> = tree.root().descendants().iter()
+            .flat_map(|node| node.child_tokens())
+            .map(|token| token.symbol().clone())
+            .filter(|symbol| matches!(*symbol, Symbol::Separator | Symbol::Or | Symbol::Exit | Symbol::Period | Symbol::Semicolon))
+            .collect();
+        assert_eq!(symbols, vec![Symbol::Separator, Symbol::Or, Symbol::Or, Symbol::Exit, Symbol::Period, Symbol::Exit, Symbol::Exit, Symbol::Semicolon]);
+    }
+
+    #[test]
+    fn nodes_start_and_end_at_tokens() {
+        let tree = parse(SOURCE);
+        let class = tree.root().child_nodes().remove(0);
+        assert_eq!(class.kind(), SyntaxKind::Class);
+        assert!(class.text().starts_with("Square") && class.text().ends_with(')'));
+
+        let kinds: Vec<_> = class.child_nodes().iter().map(|node| node.kind()).collect();
+        assert_eq!(kinds, vec![SyntaxKind::Locals, SyntaxKind::Method, SyntaxKind::Method, SyntaxKind::Method]);
+        assert_eq!(format!("{:?}", class.child_nodes()[1]), "Method@5:5..5:31");
+    }
+
+    #[test]
+    fn spans_count_characters_across_lines() {
+        let tree = parse("Test = ( run = ( ^ 'é\né' , x ) )");
+        let token = tree.root().descendants().iter()
+            .flat_map(|node| node.child_tokens())
+            .find(|token| token.text() == "x")
+            .unwrap();
+        assert_eq!(format!("{:?}", token.span()), "2:6..2:7");
+        assert_eq!(token.span().start, Location(2, 6, 29));
+        assert_eq!(tree.root().span().end, Location(2, 11, 34));
+    }
+
+    #[test]
+    fn lowers_to_parser_ast() {
+        let mut parser = Parser::new(SOURCE.as_bytes(), "test").with_trivia().with_options(options());
+        let expected = parser.parse_class();
+        assert_eq!(parse(SOURCE).lower(&mut parser.into_interner()), expected);
+    }
+
+    #[test]
+    fn rewrites_tokens_keeping_formatting() {
+        let tree = parse(SOURCE);
+        let token = tree.root().descendants().iter()
+            .flat_map(|node| node.child_tokens())
+            .find(|token| token.text() == "area")
+            .unwrap();
+
+        let green = token.replace_with(GreenToken::new(Token(Symbol::Identifier, Some("surface".to_string())), "surface"));
+        let rewritten = SyntaxTree::from_green(green, options());
+        assert_eq!(rewritten.text(), SOURCE.replace("area =", "surface ="));
+        let mut interner = Interner::new();
+        let class = rewritten.lower(&mut interner).unwrap();
+        assert!(class.instance_methods.contains_key(&interner.intern("surface")));
+    }
+
+    #[test]
+    fn rejects_invalid_source() {
+        assert!(SyntaxTree::parse("Test = ( run = ( ^ ) )", options()).is_err());
+        assert!(SyntaxTree::parse("Test = ( run = ( 'open ) )", options()).is_err());
+        assert!(SyntaxTree::parse("Test = ( run = ( ^ 1 )", options()).is_err());
+    }
+
+    #[test]
+    fn lowering_rejects_malformed_trees() {
+        let empty_class = GreenNode::new(SyntaxKind::Root, vec![GreenNode::new(SyntaxKind::Class, vec![]).into()]);
+        assert!(SyntaxTree::from_green(empty_class, options()).lower(&mut Interner::new()).is_err());
+
+        let tree = parse("Test = ( run = ( ^ a ) )");
+        let token = tree.root().descendants().iter()
+            .flat_map(|node| node.child_tokens())
+            .find(|token| token.text() == "a")
+            .unwrap();
+        let green = token.replace_with(GreenToken::new(Token::from(Symbol::Period), "."));
+        assert_eq!(SyntaxTree::from_green(green, options()).lower(&mut Interner::new()), Err(Error::UnexpectedTokenError {
+            text: ".".to_string(),
+            location: Location(1, 20, 19),
+        }));
+
+        let variable = tree.root().descendants().into_iter().find(|node| node.kind() == SyntaxKind::Variable).unwrap();
+        for &kind in &[SyntaxKind::Locals, SyntaxKind::Method, SyntaxKind::UnaryMessage, SyntaxKind::Cascade, SyntaxKind::Symbol] {
+            let green = variable.replace_with(GreenNode::new(kind, vec![]));
+            assert!(SyntaxTree::from_green(green, options()).lower(&mut Interner::new()).is_err());
+        }
+    }
+
+    #[test]
+    fn lowering_never_panics_on_rewritten_trees() {
+        let tree = parse(SOURCE);
+        let kinds = [SyntaxKind::Root, SyntaxKind::Class, SyntaxKind::Body, SyntaxKind::Block, SyntaxKind::BlockParameters,
+                     SyntaxKind::Assignment, SyntaxKind::KeywordMessage, SyntaxKind::CascadeMessage, SyntaxKind::LiteralArray];
+        let tokens = [Token::from(Symbol::Period), Token::from(Symbol::EndTerm), Token(Symbol::Keyword, Some("at:".to_string()))];
+        for node in tree.root().descendants() {
+            for &kind in &kinds {
+                let _ = SyntaxTree::from_green(node.replace_with(GreenNode::new(kind, vec![])), options()).lower(&mut Interner::new());
+            }
+
+            for token in node.child_tokens() {
+                for replacement in &tokens {
+                    let green = token.replace_with(GreenToken::new(replacement.clone(), &replacement.text()));
+                    let _ = SyntaxTree::from_green(green, options()).lower(&mut Interner::new());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_tokens_after_class() {
+        assert_eq!(SyntaxTree::parse("Test = ( ) junk 1 2", options()).err(), Some(Error::UnexpectedTokenError {
+            text: "junk".to_string(),
+            location: Location(1, 12, 11),
+        }));
+        assert_eq!(parse("Test = ( ) \"trailing\"\n").text(), "Test = ( ) \"trailing\"\n");
+    }
+
+    #[derive(Clone, Debug)]
+    struct ArbitrarySource(String);
+
+    impl Arbitrary for ArbitrarySource {
+        fn arbitrary(g: &mut Gen) -> ArbitrarySource {
+            let mut source = g.choose(&["", "Test = ( ", "Test = Object ( | a | ", "Test = ( run = ( "]).unwrap().to_string();
+            for _ in 0..usize::arbitrary(g) % 16 {
+                source += g.choose(&FRAGMENTS).unwrap();
+                source.push(' ');
+            }
+
+            source += g.choose(&["", " )", " ) )", " ) ) )"]).unwrap();
+            ArbitrarySource(source)
+        }
+    }
+
+    #[test]
+    fn agrees_with_parser() {
+        fn property(source: ArbitrarySource, dialect: ArbitraryDialect, cascades: bool) -> bool {
+            let options = ParserOptions { cascades, ..dialect.0.options() };
+            let mut parser = Parser::new(source.0.as_bytes(), "test").with_trivia().with_options(options.clone());
+            let expected = parser.parse_class();
+            let mut interner = parser.into_interner();
+            let lowered = SyntaxTree::parse(&source.0, options).and_then(|tree| tree.lower(&mut interner));
+            match (expected, lowered) {
+                (Ok(expected), Ok(lowered)) => expected == lowered,
+                (expected, lowered) => expected.is_err() && lowered.is_err(),
+            }
+        }
+
+        QuickCheck::new().tests(1000).quickcheck(property as fn(ArbitrarySource, ArbitraryDialect, bool) -> bool);
+    }
+}
@@ -0,0 +1,68 @@
+use compiler::ast::ExpressionKind;
+use compiler::{Symbol, Token};
+use num_bigint::BigInt;
+
+pub const BINARY_OPERATORS: [Symbol; 14] = [
+    Symbol::And, Symbol::At, Symbol::Comma, Symbol::Divide, Symbol::Equal,
+    Symbol::Less, Symbol::Minus, Symbol::Modulus, Symbol::More, Symbol::Not,
+    Symbol::Or, Symbol::Percent, Symbol::Plus, Symbol::Star,
+];
+
+pub const RESERVED_NAMES: [&str; 5] = ["self", "super", "nil", "true", "false"];
+
+pub fn is_binary_operator(symbol: &Symbol) -> bool {
+    *symbol == Symbol::OperatorSequence || BINARY_OPERATORS.contains(symbol)
+}
+
+pub fn is_pattern_start(symbol: &Symbol) -> bool {
+    matches!(*symbol, Symbol::Identifier | Symbol::Keyword) || is_binary_operator(symbol)
+}
+
+pub fn is_literal(symbol: &Symbol) -> bool {
+    matches!(*symbol, Symbol::String | Symbol::Integer | Symbol::Double | Symbol::Character)
+}
+
+pub fn is_trivia(symbol: &Symbol) -> bool {
+    matches!(*symbol, Symbol::Comment | Symbol::Whitespace)
+}
+
+pub fn is_reserved_name(name: &str) -> bool {
+    RESERVED_NAMES.contains(&name)
+}
+
+pub fn number_literal(token: &Token) -> Option<ExpressionKind> {
+    let text = token.text();
+    match token.0 {
+        Symbol::Integer => text.parse().map(ExpressionKind::LiteralInteger)
+            .or_else(|_| text.parse::<BigInt>().map(ExpressionKind::LiteralBigInteger))
+            .ok(),
+        Symbol::Double => text.parse().ok().filter(|value: &f64| value.is_finite()).map(ExpressionKind::LiteralDouble),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_binary_operator, is_pattern_start, is_reserved_name, number_literal};
+    use compiler::ast::ExpressionKind;
+    use compiler::{Symbol, Token};
+
+    #[test]
+    fn classifies_symbols() {
+        assert!(is_binary_operator(&Symbol::Minus) && is_binary_operator(&Symbol::OperatorSequence));
+        assert!(!is_binary_operator(&Symbol::Keyword) && !is_binary_operator(&Symbol::Semicolon));
+        assert!(is_pattern_start(&Symbol::Keyword) && is_pattern_start(&Symbol::Or));
+        assert!(!is_pattern_start(&Symbol::Separator));
+        assert!(is_reserved_name("super") && !is_reserved_name("Object"));
+    }
+
+    #[test]
+    fn converts_number_literals() {
+        let number = |symbol: Symbol, text: &str| number_literal(&Token(symbol, Some(text.to_string())));
+        assert_eq!(number(Symbol::Integer, "-42"), Some(ExpressionKind::LiteralInteger(-42)));
+        assert_eq!(number(Symbol::Integer, "9223372036854775808"), Some(ExpressionKind::LiteralBigInteger("9223372036854775808".parse().unwrap())));
+        assert_eq!(number(Symbol::Double, "2.5e1"), Some(ExpressionKind::LiteralDouble(25.0)));
+        assert_eq!(number(Symbol::Double, "1e400"), None);
+        assert_eq!(number(Symbol::String, "1"), None);
+    }
+}
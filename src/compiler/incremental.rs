@@ -0,0 +1,320 @@
+use compiler::ast::{self, Block, Expression, ExpressionKind, Method};
+use compiler::interner::Interner;
+use compiler::visit::{walk_block_mut, walk_expression_mut, walk_method_mut, VisitorMut};
+use compiler::{Error, Parser, ParserOptions};
+use util::location::{Location, Span};
+use std::mem;
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: &str) -> TextEdit {
+        TextEdit { range, text: text.to_string() }
+    }
+
+    pub fn apply(&self, source: &str) -> String {
+        let mut result = String::with_capacity(source.len() + self.text.len());
+        result.push_str(&source[..self.range.start]);
+        result.push_str(&self.text);
+        result.push_str(&source[self.range.end..]);
+        result
+    }
+
+    fn shift(&self, offset: usize) -> usize {
+        if offset >= self.range.end {
+            offset - self.range.end + self.range.start + self.text.len()
+        } else {
+            offset
+        }
+    }
+}
+
+struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> LineIndex<'a> {
+        let starts = Some(0).into_iter().chain(source.match_indices('\n').map(|(index, _)| index + 1)).collect();
+        LineIndex { source, starts }
+    }
+
+    fn location(&self, offset: usize) -> Location {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let start = self.starts[line - 1];
+        Location(line, self.source[start..offset].chars().count() + 1, offset)
+    }
+}
+
+struct Relocate<'a, F: Fn(usize) -> usize> {
+    lines: &'a LineIndex<'a>,
+    offset: F,
+}
+
+impl<'a, F: Fn(usize) -> usize> Relocate<'a, F> {
+    fn span(&self, span: Span) -> Span {
+        let start = self.lines.location((self.offset)(span.start.offset()));
+        let end = self.lines.location((self.offset)(span.end.offset()));
+        Span::new(start, end)
+    }
+
+    fn spans(&self, spans: &mut [Span]) {
+        for span in spans {
+            *span = self.span(*span);
+        }
+    }
+}
+
+impl<'a, F: Fn(usize) -> usize> VisitorMut for Relocate<'a, F> {
+    fn visit_method_mut(&mut self, method: &mut Method) {
+        match *method {
+            Method::Primitive { ref mut span, ref mut parameter_spans, .. } => {
+                *span = self.span(*span);
+                self.spans(parameter_spans);
+            }
+            Method::Native { ref mut span, .. } => *span = self.span(*span),
+        }
+
+        walk_method_mut(self, method);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        block.span = self.span(block.span);
+        self.spans(&mut block.parameter_spans);
+        walk_block_mut(self, block);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        expression.span = self.span(expression.span);
+        if let ExpressionKind::Assignment { ref mut variable_spans, .. } = expression.kind {
+            self.spans(variable_spans);
+        }
+
+        walk_expression_mut(self, expression);
+    }
+}
+
+pub struct IncrementalParser<P: AsRef<Path>> {
+    filename: P,
+    options: ParserOptions,
+    trivia: bool,
+    interner: Interner,
+}
+
+impl<P: AsRef<Path>> IncrementalParser<P> {
+    pub fn new(filename: P) -> IncrementalParser<P> {
+        IncrementalParser { filename, options: ParserOptions::default(), trivia: false, interner: Interner::new() }
+    }
+
+    pub fn with_trivia(self) -> IncrementalParser<P> {
+        IncrementalParser { trivia: true, ..self }
+    }
+
+    pub fn with_options(self, options: ParserOptions) -> IncrementalParser<P> {
+        IncrementalParser { options, ..self }
+    }
+
+    pub fn with_interner(self, interner: Interner) -> IncrementalParser<P> {
+        IncrementalParser { interner, ..self }
+    }
+
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    pub fn parse(&mut self, source: &str) -> Result<ast::Class, Error> {
+        self.with_parser(source, |parser| parser.parse_class())
+    }
+
+    pub fn reparse(&mut self, previous: &ast::Class, edit: &TextEdit, source: &str) -> Result<ast::Class, Error> {
+        match self.reparse_method(previous, edit, source) {
+            Some(class) => Ok(class),
+            None => self.parse(source),
+        }
+    }
+
+    fn with_parser<T, F: FnOnce(&mut Parser<&[u8], &P>) -> T>(&mut self, source: &str, f: F) -> T {
+        let parser = Parser::new(source.as_bytes(), &self.filename)
+            .with_options(self.options.clone())
+            .with_interner(mem::take(&mut self.interner));
+        let mut parser = if self.trivia { parser.with_trivia() } else { parser };
+        let result = f(&mut parser);
+        self.interner = parser.into_interner();
+        result
+    }
+
+    fn reparse_method(&mut self, previous: &ast::Class, edit: &TextEdit, source: &str) -> Option<ast::Class> {
+        let inside = |method: &&Method| {
+            let span = method.span();
+            span.start.offset() < edit.range.start && edit.range.end < span.end.offset()
+        };
+
+        let (old, instance_side) = match previous.instance_methods.iter().find(inside) {
+            Some(method) => (method, true),
+            None => (previous.class_methods.iter().find(inside)?, false),
+        };
+
+        let start = old.span().start.offset();
+        let region = source.get(start..edit.shift(old.span().end.offset()))?;
+        let mut method = self.with_parser(region, |parser| parser.parse_standalone_method()).ok()?;
+
+        let lines = LineIndex::new(source);
+        Relocate { lines: &lines, offset: |offset| offset + start }.visit_method_mut(&mut method);
+        match method {
+            Method::Primitive { ref mut comments, .. } | Method::Native { ref mut comments, .. } => {
+                *comments = old.comments().to_vec();
+            }
+        }
+
+        let mut relocate = Relocate { lines: &lines, offset: |offset| edit.shift(offset) };
+        let mut rebuild = |methods: &ast::MethodTable, side: bool| -> Option<ast::MethodTable> {
+            ast::MethodTable::from_methods(methods.iter().map(|existing| {
+                if side == instance_side && existing.span() == old.span() {
+                    return method.clone();
+                }
+
+                let mut existing = existing.clone();
+                if existing.span().start.offset() >= edit.range.end {
+                    relocate.visit_method_mut(&mut existing);
+                }
+
+                existing
+            })).ok()
+        };
+
+        let instance_methods = rebuild(&previous.instance_methods, true)?;
+        let class_methods = rebuild(&previous.class_methods, false)?;
+        let span = Span::new(previous.span.start, lines.location(edit.shift(previous.span.end.offset())));
+
+        Some(ast::Class {
+            name: previous.name,
+            superclass: previous.superclass.clone(),
+            instance_methods,
+            class_methods,
+            instance_variables: previous.instance_variables.clone(),
+            class_variables: previous.class_variables.clone(),
+            comments: previous.comments.clone(),
+            span,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IncrementalParser, TextEdit};
+    use compiler::{Dialect, ParserOptions};
+    use quickcheck::{Arbitrary, Gen, QuickCheck};
+
+    const SOURCE: &str = "\"Counts things\"
+Counter = Object (
+    | count step |
+
+    \"Advance the counter\"
+    increment = (
+        count := count + step.
+        ^ self
+    )
+
+    at: index put: value = ( ^ [ :x | x + index ] value: value )
+    printString = primitive
+
+    ----
+
+    | instances |
+
+    new = ( instances := instances + 1. ^ super new ) other = ( ^ -1 )
+)
+";
+
+    fn check(source: &str, edit: &TextEdit) -> bool {
+        let mut parser = IncrementalParser::new("test").with_trivia();
+        let previous = parser.parse(source).unwrap();
+        let updated = edit.apply(source);
+        let incremental = parser.reparse(&previous, edit, &updated);
+        assert_eq!(incremental, parser.parse(&updated), "edit {:?}", edit);
+        parser.reparse_method(&previous, edit, &updated).is_some()
+    }
+
+    fn replace(pattern: &str, text: &str) -> TextEdit {
+        let start = SOURCE.find(pattern).unwrap();
+        TextEdit::new(start..start + pattern.len(), text)
+    }
+
+    #[test]
+    fn applies_edits() {
+        let edit = TextEdit::new(2..4, "xyz");
+        assert_eq!(edit.apply("abcdef"), "abxyzef");
+    }
+
+    #[test]
+    fn reuses_methods_around_edited_body() {
+        assert!(check(SOURCE, &replace("count + step", "count\n            + step * 2")));
+        assert!(check(SOURCE, &replace("^ self\n", "")));
+        assert!(check(SOURCE, &replace("x + index", "x")));
+        assert!(check(SOURCE, &replace("instances + 1", "instances - 1")));
+    }
+
+    #[test]
+    fn reparses_renamed_and_reshaped_methods() {
+        assert!(check(SOURCE, &replace("crement", "crementBy: n")));
+        assert!(check(SOURCE, &replace("String =", "String: stream on: aFile =")));
+        assert!(check(SOURCE, &replace("^ -1", "^ #(-1 2)")));
+    }
+
+    #[test]
+    fn falls_back_outside_method_bodies() {
+        assert!(!check(SOURCE, &replace("count step", "count step limit")));
+        assert!(!check(SOURCE, &replace("Advance", "Step")));
+        assert!(!check(SOURCE, &replace("----", "")));
+        assert!(!check(SOURCE, &replace("printString", "increment")));
+    }
+
+    #[test]
+    fn falls_back_when_body_no_longer_parses() {
+        assert!(!check(SOURCE, &replace("^ self\n    )", "^ self\n    ) extra = ( ^ 1")));
+        assert!(!check(SOURCE, &replace("count := count + step.", "count := (count + step.")));
+        assert!(!check(SOURCE, &replace("x + index ]", "x + index")));
+    }
+
+    #[test]
+    fn uses_parser_options() {
+        let mut parser = IncrementalParser::new("test").with_options(ParserOptions { cascades: true, ..Dialect::SomJava.into() });
+        let source = "Test = ( run = ( ^ self foo ) )";
+        let previous = parser.parse(source).unwrap();
+        let edit = TextEdit::new(24..27, "foo; bar");
+        let updated = edit.apply(source);
+        assert_eq!(parser.reparse(&previous, &edit, &updated), parser.parse(&updated));
+        assert!(parser.reparse_method(&previous, &edit, &updated).is_some());
+    }
+
+    #[derive(Clone, Debug)]
+    struct ArbitraryEdit(TextEdit);
+
+    impl Arbitrary for ArbitraryEdit {
+        fn arbitrary(g: &mut Gen) -> ArbitraryEdit {
+            let snippets = ["", " ", "\n", "x", "1", "-", ".", "^ ", "(", ")", "[", "]", "| a |", ":=", "foo: 2", "'s'", "\"c\"", "é"];
+            let boundaries: Vec<_> = (0..=SOURCE.len()).filter(|&index| SOURCE.is_char_boundary(index)).collect();
+            let start = *g.choose(&boundaries).unwrap();
+            let end = (start + usize::arbitrary(g) % 8).min(SOURCE.len());
+            let end = (end..=SOURCE.len()).find(|&index| SOURCE.is_char_boundary(index)).unwrap();
+            ArbitraryEdit(TextEdit::new(start..end, g.choose(&snippets).unwrap()))
+        }
+    }
+
+    #[test]
+    fn reparse_matches_fresh_parse() {
+        fn property(edit: ArbitraryEdit) -> bool {
+            check(SOURCE, &edit.0);
+            true
+        }
+
+        QuickCheck::new().tests(1000).quickcheck(property as fn(ArbitraryEdit) -> bool);
+    }
+}
@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Name(u32);
+
+thread_local! {
+    static RESOLVING: RefCell<Vec<Arc<str>>> = const { RefCell::new(Vec::new()) };
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        RESOLVING.with(|strings| match strings.borrow().get(self.0 as usize) {
+            Some(text) => fmt::Debug::fmt(&**text, f),
+            None => write!(f, "Name({})", self.0),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    names: HashMap<Arc<str>, Name>,
+    strings: Vec<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Default::default()
+    }
+
+    pub fn intern(&mut self, text: &str) -> Name {
+        if let Some(&name) = self.names.get(text) {
+            return name;
+        }
+
+        let name = Name(self.strings.len() as u32);
+        let text: Arc<str> = Arc::from(text);
+        self.strings.push(text.clone());
+        self.names.insert(text, name);
+        name
+    }
+
+    pub fn get(&self, text: &str) -> Option<Name> {
+        self.names.get(text).cloned()
+    }
+
+    pub fn resolve(&self, name: Name) -> &str {
+        &self.strings[name.0 as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub fn debug<'a, T: fmt::Debug + ?Sized>(&'a self, value: &'a T) -> Resolved<'a, T> {
+        Resolved(self, value)
+    }
+}
+
+pub struct Resolved<'a, T: ?Sized>(&'a Interner, &'a T);
+
+impl<'a, T: fmt::Debug + ?Sized> fmt::Debug for Resolved<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let previous = RESOLVING.with(|strings| strings.replace(self.0.strings.clone()));
+        let result = self.1.fmt(f);
+        RESOLVING.with(|strings| strings.replace(previous));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interner;
+
+    #[test]
+    fn interning_is_idempotent() {
+        let mut interner = Interner::new();
+        let first = interner.intern("at:put:");
+        let second = interner.intern("at:put:");
+        assert_eq!(first, second);
+        assert_eq!(interner.get("at:put:"), Some(first));
+        assert_eq!(interner.len(), 1);
+    }
+
+    #[test]
+    fn distinct_text_gets_distinct_names() {
+        let mut interner = Interner::new();
+        assert!(interner.intern("println") != interner.intern("print"));
+        assert_eq!(interner.get("printString"), None);
+    }
+
+    #[test]
+    fn resolve_returns_text() {
+        let mut interner = Interner::new();
+        let name = interner.intern("value:value:");
+        assert_eq!(interner.resolve(name), "value:value:");
+        assert_eq!(format!("{:?}", name), "Name(0)");
+        assert_eq!(format!("{:?}", interner.debug(&[name])), "[\"value:value:\"]");
+        assert_eq!(format!("{:?}", name), "Name(0)");
+    }
+}
@@ -1,56 +1,111 @@
-use compiler::{Symbol, Token};
-use std::ascii::AsciiExt;
-use std::collections::VecDeque;
-use std::io;
-use std::io::{BufRead};
-use util::PeekableBuffer;
-use util::peekable_buffer::Location;
+use compiler::{BorrowedToken, ParserOptions, Symbol, Token};
+use compiler::grammar::is_binary_operator;
+use compiler::interner::{Interner, Name};
+use num_bigint::BigInt;
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+use std::str;
+use std::vec;
+use util::location::{Location, Span};
 
 fn is_operator(c: char) -> bool {
-    match c {
-        '~' | '&' | '|' | '*' | '/' | '\\' | '+' | '=' | '>' | '<' | ',' | '@' | '%' => true,
-        _ => false,
-    }
+    matches!(c, '~' | '&' | '|' | '*' | '/' | '\\' | '+' | '=' | '>' | '<' | ',' | '@' | '%')
+}
+
+const MAX_EXPONENT: i32 = 1024;
+
+fn ends_operand(symbol: &Symbol) -> bool {
+    matches!(*symbol,
+        Symbol::Character | Symbol::Double | Symbol::EndBlock | Symbol::EndTerm | Symbol::Identifier |
+        Symbol::Integer | Symbol::KeywordSequence | Symbol::String)
 }
 
 fn is_identifier(c: char) -> bool {
     c.is_ascii() && (c.is_alphanumeric() || c == '_')
 }
 
-#[derive(Debug)]
-enum Error {
-    IoError(io::Error),
+pub fn escape_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('\'');
+    for c in text.chars() {
+        match c {
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '\0' => escaped.push_str("\\0"),
+            '\'' => escaped.push_str("\\'"),
+            '\\' => escaped.push_str("\\\\"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('\'');
+    escaped
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    UnknownCharacterError { text: String, location: Location },
+    UnterminatedCommentError { location: Location },
+    UnterminatedStringError { location: Location },
+    InvalidEscapeError { text: String, location: Location },
+    InvalidNumberError { text: String, location: Location },
+    InvalidUtf8Error { location: Location },
+    IoError { kind: io::ErrorKind, description: String },
     End,
 }
 
+impl Error {
+    pub fn location(&self) -> Option<Location> {
+        match *self {
+            Error::UnknownCharacterError { location, .. } |
+            Error::UnterminatedCommentError { location } |
+            Error::UnterminatedStringError { location } |
+            Error::InvalidEscapeError { location, .. } |
+            Error::InvalidNumberError { location, .. } |
+            Error::InvalidUtf8Error { location } => Some(location),
+            Error::IoError { .. } | Error::End => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
-pub struct Item(pub Token, pub Location);
+pub struct Item(pub Token, pub Span, pub Option<Name>);
 
 impl PartialEq<Token> for Item {
     fn eq(&self, other: &Token) -> bool {
-        let &Item(ref token, _) = self;
+        let Item(token, _, _) = self;
         other == token
     }
 }
 
-impl From<io::Error> for Error {
-    fn from(err: io::Error) -> Error {
-        Error::IoError(err)
+#[derive(Clone, Debug, PartialEq)]
+pub struct BorrowedItem<'a>(pub BorrowedToken<'a>, pub Span);
+
+impl<'a> BorrowedItem<'a> {
+    pub fn into_owned(self) -> Item {
+        let BorrowedItem(token, span) = self;
+        Item(token.into_owned(), span, None)
     }
 }
 
 pub struct Lexer<R: BufRead> {
-    buffer: PeekableBuffer<R>,
-    queue: VecDeque<Item>,
+    reader: Option<R>,
+    items: vec::IntoIter<Result<Item, Error>>,
+    trivia: bool,
+    options: ParserOptions,
+    interner: Interner,
 }
 
 impl<R: BufRead> Iterator for Lexer<R> {
-    type Item = Item;
+    type Item = Result<Item, Error>;
 
-    fn next(&mut self) -> Option<Item> {
+    fn next(&mut self) -> Option<Result<Item, Error>> {
         match self.read_token() {
-            Ok(i) => Some(i),
-            Err(_) => None
+            Err(Error::End) => None,
+            result => Some(result),
         }
     }
 }
@@ -58,29 +113,156 @@ impl<R: BufRead> Iterator for Lexer<R> {
 impl<R: BufRead> Lexer<R> {
     pub fn new(reader: R) -> Lexer<R> {
         Lexer {
-            buffer: PeekableBuffer::new(reader),
-            queue: VecDeque::new(),
+            reader: Some(reader),
+            items: Vec::new().into_iter(),
+            trivia: false,
+            options: ParserOptions::default(),
+            interner: Interner::new(),
         }
     }
 
+    pub fn with_trivia(self) -> Lexer<R> {
+        Lexer { trivia: true, ..self }
+    }
+
+    pub fn with_options(self, options: ParserOptions) -> Lexer<R> {
+        Lexer { options, ..self }
+    }
+
+    pub fn with_interner(self, interner: Interner) -> Lexer<R> {
+        Lexer { interner, ..self }
+    }
+
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    pub fn into_interner(self) -> Interner {
+        self.interner
+    }
+
+    pub fn intern(&mut self, text: &str) -> Name {
+        self.interner.intern(text)
+    }
+
     fn read_token(&mut self) -> Result<Item, Error> {
-        if !self.queue.is_empty() {
-            return Ok(self.queue.pop_front().unwrap());
+        if let Some(reader) = self.reader.take() {
+            self.items = self.read_items(reader).into_iter();
+        }
+
+        self.items.next().unwrap_or(Err(Error::End))
+    }
+
+    fn read_items(&mut self, mut reader: R) -> Vec<Result<Item, Error>> {
+        let mut bytes = vec![];
+        if let Err(e) = reader.read_to_end(&mut bytes) {
+            return vec![Err(Error::IoError { kind: e.kind(), description: e.to_string() })];
+        }
+
+        let source = match String::from_utf8(bytes) {
+            Ok(source) => source,
+            Err(e) => {
+                let valid = &e.as_bytes()[..e.utf8_error().valid_up_to()];
+                let mut lexer = SourceLexer::new(str::from_utf8(valid).unwrap_or_default());
+                while lexer.advance().is_some() {}
+                return vec![Err(Error::InvalidUtf8Error { location: lexer.location })];
+            }
+        };
+
+        let mut lexer = SourceLexer { trivia: self.trivia, ..SourceLexer::new(&source).with_options(self.options.clone()) };
+        let mut items = vec![];
+        loop {
+            let location = lexer.location;
+            match lexer.read_token() {
+                Ok(BorrowedItem(token, span)) => {
+                    let token = token.into_owned();
+                    let name = match token.0 {
+                        Symbol::Identifier | Symbol::Keyword | Symbol::KeywordSequence => Some(self.interner.intern(&token.text())),
+                        ref symbol if is_binary_operator(symbol) => Some(self.interner.intern(&token.text())),
+                        _ => None,
+                    };
+                    items.push(Ok(Item(token, span, name)));
+                }
+                Err(Error::End) => break,
+                Err(e) => {
+                    items.push(Err(e));
+                    if lexer.location == location {
+                        break;
+                    }
+                }
+            }
+        }
+
+        items
+    }
+}
+
+pub struct SourceLexer<'a> {
+    source: &'a str,
+    location: Location,
+    trivia: bool,
+    options: ParserOptions,
+    operand: bool,
+    pound: bool,
+    arrays: usize,
+}
+
+impl<'a> Iterator for SourceLexer<'a> {
+    type Item = Result<BorrowedItem<'a>, Error>;
+
+    fn next(&mut self) -> Option<Result<BorrowedItem<'a>, Error>> {
+        match self.read_token() {
+            Err(Error::End) => None,
+            result => Some(result),
+        }
+    }
+}
+
+impl<'a> SourceLexer<'a> {
+    pub fn new(source: &'a str) -> SourceLexer<'a> {
+        SourceLexer {
+            source,
+            location: Location(1, 1, 0),
+            trivia: false,
+            options: ParserOptions::default(),
+            operand: false,
+            pound: false,
+            arrays: 0,
+        }
+    }
+
+    pub fn with_trivia(self) -> SourceLexer<'a> {
+        SourceLexer { trivia: true, ..self }
+    }
+
+    pub fn with_options(self, options: ParserOptions) -> SourceLexer<'a> {
+        SourceLexer { options, ..self }
+    }
+
+    pub fn from_bytes(source: &'a [u8]) -> Result<SourceLexer<'a>, str::Utf8Error> {
+        str::from_utf8(source).map(SourceLexer::new)
+    }
+
+    fn read_token(&mut self) -> Result<BorrowedItem<'a>, Error> {
+        if self.trivia {
+            if let Some(item) = self.read_trivia()? {
+                return Ok(item);
+            }
         }
 
         loop {
             self.skip_whitespace();
-            self.skip_comments();
+            self.skip_comments()?;
 
-            if self.buffer.peek().map_or(false, |c| c.is_whitespace()) {
+            if self.peek().is_some_and(|c| c.is_whitespace()) {
                 continue;
             } else {
                 break;
             }
         }
 
-        let location = self.buffer.location();
-        let c = match self.buffer.peek() {
+        let location = self.location;
+        let c = match self.peek() {
             Some(c) => c,
             None => return Err(Error::End),
         };
@@ -91,64 +273,123 @@ impl<R: BufRead> Lexer<R> {
             '(' => self.read_symbol(Symbol::NewTerm),
             ')' => self.read_symbol(Symbol::EndTerm),
             '#' => self.read_symbol(Symbol::Pound),
+            '$' => self.read_character()?,
             '^' => self.read_symbol(Symbol::Exit),
             '.' => self.read_symbol(Symbol::Period),
-            '-' => self.read_minus(),
+            ';' => self.read_symbol(Symbol::Semicolon),
+            '-' => self.read_minus()?,
             ':' => self.read_colon(),
-            'a'...'z' | 'A'...'Z' => self.read_identifier(),
-            '0'...'9' => self.read_number(),
-            '\'' => self.read_string(),
+            'a'..='z' | 'A'..='Z' => self.read_identifier(),
+            '0'..='9' => self.read_number()?,
+            '\'' => self.read_string()?,
             c if is_operator(c) => self.read_operator(),
-            c  => panic!("do not understand: {:?}", c)
+            c => {
+                self.advance();
+                return Err(Error::UnknownCharacterError { text: c.to_string(), location });
+            }
+        };
+
+        match token.0 {
+            Symbol::NewTerm if self.pound || self.arrays > 0 => self.arrays += 1,
+            Symbol::EndTerm if self.arrays > 0 => self.arrays -= 1,
+            _ => {}
+        }
+
+        let symbol_literal = self.pound && token.0 != Symbol::NewTerm;
+        self.pound = token.0 == Symbol::Pound;
+        self.operand = self.arrays == 0 && (symbol_literal || ends_operand(&token.0));
+        Ok(BorrowedItem(token, Span::new(location, self.location)))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.location.offset()..]
+    }
+
+    fn text_from(&self, start: Location) -> &'a str {
+        &self.source[start.offset()..self.location.offset()]
+    }
+
+    fn peek(&self) -> Option<char> {
+        match self.source.as_bytes().get(self.location.offset()) {
+            Some(&b) if b.is_ascii() => Some(b as char),
+            Some(_) => self.rest().chars().next(),
+            None => None,
+        }
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        let Location(line, column, offset) = self.location;
+        self.location = if c == '\n' {
+            Location(line + 1, 1, offset + 1)
+        } else {
+            Location(line, column + 1, offset + c.len_utf8())
         };
 
-        Ok(Item(token, location))
+        Some(c)
     }
 
     fn skip_whitespace(&mut self) {
-        loop {
-            match self.buffer.peek() {
-                Some(c) if c.is_whitespace() => self.buffer.consume(),
-                _ => break
-            }
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.advance();
+        }
+    }
+
+    fn skip_digits(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.advance();
         }
     }
 
-    fn skip_comments(&mut self) {
-        if self.buffer.peek() != Some('"') {
-            return;
+    fn skip_comments(&mut self) -> Result<(), Error> {
+        if self.peek() != Some('"') {
+            return Ok(());
         }
 
-        self.buffer.consume();
+        let location = self.location;
+        self.advance();
         loop {
-            if self.buffer.next() == Some('"') {
-                break;
+            match self.advance() {
+                Some('"') => return Ok(()),
+                Some(_) => continue,
+                None => return Err(Error::UnterminatedCommentError { location }),
             }
         }
     }
 
-    fn read_symbol(&mut self, symbol: Symbol) -> Token {
-        self.buffer.consume();
-        From::from(symbol)
+    fn read_trivia(&mut self) -> Result<Option<BorrowedItem<'a>>, Error> {
+        let start = self.location;
+        let symbol = match self.peek() {
+            Some('"') => {
+                self.skip_comments()?;
+                Symbol::Comment
+            }
+            Some(c) if c.is_whitespace() => {
+                self.skip_whitespace();
+                Symbol::Whitespace
+            }
+            _ => return Ok(None),
+        };
+
+        let token = BorrowedToken(symbol, Some(Cow::Borrowed(self.text_from(start))));
+        Ok(Some(BorrowedItem(token, Span::new(start, self.location))))
     }
 
-    fn read_operator(&mut self) -> Token {
-        let c = self.buffer.next().unwrap();
-        let mut sequence = String::new();
-        sequence.push(c);
+    fn read_symbol(&mut self, symbol: Symbol) -> BorrowedToken<'a> {
+        self.advance();
+        From::from(symbol)
+    }
 
-        loop {
-            match self.buffer.peek() {
-                Some(ch) if is_operator(ch) => {
-                    self.buffer.consume();
-                    sequence.push(ch);
-                }
-                _ => break,
-            }
+    fn read_operator(&mut self) -> BorrowedToken<'a> {
+        let start = self.location;
+        let c = self.advance().unwrap();
+        while self.peek().is_some_and(is_operator) {
+            self.advance();
         }
 
+        let sequence = self.text_from(start);
         if sequence.len() > 1 {
-            Token(Symbol::OperatorSequence, Some(sequence))
+            BorrowedToken(Symbol::OperatorSequence, Some(Cow::Borrowed(sequence)))
         } else {
             match c {
                 '~' => From::from(Symbol::Not),
@@ -169,145 +410,220 @@ impl<R: BufRead> Lexer<R> {
         }
     }
 
-    fn read_colon(&mut self) -> Token {
-        self.buffer.consume();
-        if self.buffer.peek() == Some('=') {
-            self.buffer.consume();
+    fn read_colon(&mut self) -> BorrowedToken<'a> {
+        self.advance();
+        if self.peek() == Some('=') {
+            self.advance();
             From::from(Symbol::Assign)
         } else {
             From::from(Symbol::Colon)
         }
     }
 
-    fn read_identifier(&mut self) -> Token {
-        let mut text = String::new();
-        loop {
-            match self.buffer.peek() {
-                Some(c) if is_identifier(c) => {
-                    text.push(c);
-                    self.buffer.consume();
-                }
-                _ => break,
-            }
+    fn read_identifier(&mut self) -> BorrowedToken<'a> {
+        let start = self.location;
+        while self.peek().is_some_and(is_identifier) {
+            self.advance();
         }
 
-        if self.buffer.peek() == Some(':') {
-            self.buffer.consume();
-            text.push(':');
+        if self.peek() == Some(':') {
+            self.advance();
 
-            let saw_sequence = self.buffer.peek().and_then(|c| {
-                Some(c.is_alphabetic() && c.is_ascii())
-            }).unwrap_or(false);
+            let saw_sequence = self.options.keyword_sequences && self.peek().is_some_and(|c| c.is_ascii_alphabetic());
             if saw_sequence {
-                loop {
-                    match self.buffer.peek() {
-                        Some(c @ 'a'...'z') | Some(c @ 'A'...'Z') | Some(c @ '0'...'9') | Some(c @ ':') => {
-                            text.push(c);
-                            self.buffer.consume();
-                        }
-                        _ => break,
-                    }
+                while let Some('a'..='z' | 'A'..='Z' | '0'..='9' | ':') = self.peek() {
+                    self.advance();
                 }
 
-                Token(Symbol::KeywordSequence, Some(text))
+                BorrowedToken(Symbol::KeywordSequence, Some(Cow::Borrowed(self.text_from(start))))
             } else {
-                Token(Symbol::Keyword, Some(text))
+                BorrowedToken(Symbol::Keyword, Some(Cow::Borrowed(self.text_from(start))))
             }
-        } else if text == "primitive" {
+        } else if self.text_from(start) == "primitive" {
             From::from(Symbol::Primitive)
         } else {
-            Token(Symbol::Identifier, Some(text))
+            BorrowedToken(Symbol::Identifier, Some(Cow::Borrowed(self.text_from(start))))
         }
     }
 
-    fn read_string(&mut self) -> Token {
-        let mut text = String::new();
+    fn read_character(&mut self) -> Result<BorrowedToken<'a>, Error> {
+        let location = self.location;
+        self.advance();
 
-        self.buffer.consume();
-        loop {
-            match self.buffer.next() {
-                Some('\'') => break,
-                Some(c) => text.push(c),
-                None => break
-            }
+        let start = self.location;
+        match self.advance() {
+            Some(_) => Ok(BorrowedToken(Symbol::Character, Some(Cow::Borrowed(self.text_from(start))))),
+            None => Err(Error::UnknownCharacterError { text: "$".to_string(), location }),
         }
-
-        Token(Symbol::String, Some(text))
     }
 
-    fn read_number(&mut self) -> Token {
-        let mut text = String::new();
+    fn read_string(&mut self) -> Result<BorrowedToken<'a>, Error> {
+        let mut text: Option<String> = None;
+        let mut error = None;
 
-        loop {
-            match self.buffer.peek() {
-                Some(c @ '0'...'9') => {
-                    text.push(c);
-                    self.buffer.consume();
+        let location = self.location;
+        self.advance();
+        let start = self.location.offset();
+        let end = loop {
+            let escape_location = self.location;
+            let c = match self.advance() {
+                Some('\'') if self.peek() == Some('\'') => {
+                    self.advance();
+                    '\''
                 }
-                _ => break,
-            }
+                Some('\'') => break escape_location.offset(),
+                Some('\\') if !self.options.string_escapes => '\\',
+                Some('\\') => match self.advance() {
+                    Some('t') => '\t',
+                    Some('b') => '\u{8}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('f') => '\u{c}',
+                    Some('0') => '\0',
+                    Some('\'') => '\'',
+                    Some('\\') => '\\',
+                    Some(c) => {
+                        let text = format!("\\{}", c);
+                        error = error.or(Some(Error::InvalidEscapeError { text, location: escape_location }));
+                        continue;
+                    }
+                    None => return Err(Error::UnterminatedStringError { location }),
+                },
+                Some(c) => {
+                    if let Some(ref mut text) = text {
+                        text.push(c);
+                    }
+
+                    continue;
+                }
+                None => return Err(Error::UnterminatedStringError { location }),
+            };
+
+            let source = self.source;
+            text.get_or_insert_with(|| source[start..escape_location.offset()].to_string()).push(c);
+        };
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        let text = match text {
+            Some(text) => Cow::Owned(text),
+            None => Cow::Borrowed(&self.source[start..end]),
+        };
+
+        Ok(BorrowedToken(Symbol::String, Some(text)))
+    }
+
+    fn read_number(&mut self) -> Result<BorrowedToken<'a>, Error> {
+        let start = self.location;
+        self.skip_digits();
+
+        if self.peek() == Some('r') {
+            return self.read_radix_number(start);
         }
 
-        let saw_decimal = self.buffer.peek().map_or(false, |c| c == '.');
-        let location = self.buffer.location();
+        let saw_decimal = self.rest().starts_with('.') &&
+            self.rest()[1..].starts_with(|c: char| c.is_ascii_digit());
         if saw_decimal {
-            self.buffer.consume();
-            let saw_digit = self.buffer.peek().map_or(false, |c| c.is_digit(10));
-            if saw_digit {
-                text.push('.');
-
-                loop {
-                    match self.buffer.peek() {
-                        Some(c @ '0'...'9') => {
-                            text.push(c);
-                            self.buffer.consume();
-                        }
-                        _ => break,
-                    }
-                }
+            self.advance();
+            self.skip_digits();
+        }
 
-                Token(Symbol::Double, Some(text))
-            } else {
-                self.queue.push_back(Item(Token(Symbol::Period, None), location));
-                Token(Symbol::Integer, Some(text))
+        if self.peek() == Some('e') {
+            self.read_exponent(start)
+        } else if saw_decimal {
+            let text = self.text_from(start);
+            if !text.parse::<f64>().is_ok_and(f64::is_finite) {
+                return Err(Error::InvalidNumberError { text: text.to_string(), location: start });
             }
+
+            Ok(BorrowedToken(Symbol::Double, Some(Cow::Borrowed(text))))
         } else {
-            Token(Symbol::Integer, Some(text))
+            Ok(BorrowedToken(Symbol::Integer, Some(Cow::Borrowed(self.text_from(start)))))
         }
     }
 
-    fn read_minus(&mut self) -> Token {
-        self.buffer.consume();
-        let mut count = 1;
+    fn read_radix_number(&mut self, location: Location) -> Result<BorrowedToken<'a>, Error> {
+        let radix = self.text_from(location);
+        self.advance();
 
-        let location = self.buffer.location();
-        loop {
-            if self.buffer.peek() == Some('-') {
-                self.buffer.consume();
-                count += 1;
-            } else {
-                break;
-            }
+        let start = self.location;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.advance();
         }
 
-        if count >= 4 {
-            From::from(Symbol::Separator)
+        let digits = self.text_from(start);
+        let value = radix.parse::<u32>().ok()
+            .filter(|radix| (2..=36).contains(radix))
+            .and_then(|radix| BigInt::parse_bytes(digits.as_bytes(), radix));
+
+        match value {
+            Some(value) => Ok(BorrowedToken(Symbol::Integer, Some(Cow::Owned(value.to_string())))),
+            None => Err(Error::InvalidNumberError { text: self.text_from(location).to_string(), location }),
+        }
+    }
+
+    fn read_exponent(&mut self, location: Location) -> Result<BorrowedToken<'a>, Error> {
+        let mantissa = self.text_from(location);
+        self.advance();
+
+        let start = self.location;
+        if self.peek() == Some('-') {
+            self.advance();
+        }
+
+        self.skip_digits();
+        let exponent = self.text_from(start);
+        let text = self.text_from(location);
+        let error = || Error::InvalidNumberError { text: text.to_string(), location };
+
+        let exponent = exponent.parse::<i32>().ok()
+            .filter(|exponent| exponent.abs() <= MAX_EXPONENT)
+            .ok_or_else(error)?;
+        if mantissa.contains('.') || exponent < 0 {
+            let value = text.parse::<f64>().ok().filter(|value| value.is_finite()).ok_or_else(error)?;
+            Ok(BorrowedToken(Symbol::Double, Some(Cow::Owned(format!("{:?}", value)))))
         } else {
-            count -= 1;
-            for _ in (0..count) {
-                self.queue.push_back(Item(Token(Symbol::Minus, None), location))
+            let mantissa = mantissa.parse::<BigInt>().map_err(|_| error())?;
+            let value = mantissa * BigInt::from(10).pow(exponent as u32);
+            Ok(BorrowedToken(Symbol::Integer, Some(Cow::Owned(value.to_string()))))
+        }
+    }
+
+    fn read_minus(&mut self) -> Result<BorrowedToken<'a>, Error> {
+        let length = self.options.separator_length;
+        if self.rest().len() >= length && self.rest().bytes().take(length).all(|b| b == b'-') {
+            while self.peek() == Some('-') {
+                self.advance();
             }
 
-            From::from(Symbol::Minus)
+            return Ok(From::from(Symbol::Separator));
+        }
+
+        let start = self.location;
+        self.advance();
+        if self.operand || !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            return Ok(From::from(Symbol::Minus));
         }
+
+        let BorrowedToken(symbol, text) = self.read_number()?;
+        let text = match text {
+            Some(Cow::Owned(text)) => Cow::Owned(format!("-{}", text)),
+            _ => Cow::Borrowed(self.text_from(start)),
+        };
+
+        Ok(BorrowedToken(symbol, Some(text)))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Item, Lexer};
-    use compiler::{Symbol, Token};
-    use util::peekable_buffer::Location;
+    use super::{escape_string, BorrowedItem, Error, Item, Lexer, SourceLexer};
+    use compiler::{BorrowedToken, ParserOptions, Symbol, Token};
+    use std::borrow::Cow;
+    use std::io::{self, BufReader, Read};
+    use util::location::{Location, Span};
 
     #[test]
     fn skipping_whitespace() {
@@ -325,6 +641,67 @@ mod tests {
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("Test".to_string())));
     }
 
+    #[test]
+    fn multiline_comment() {
+        let source = "\"first\nsecond\" Hello".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("Hello".to_string())));
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let source = "Hello \"Test\nmore".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("Hello".to_string())));
+        assert_eq!(lexer.read_token(), Err(Error::UnterminatedCommentError { location: Location(1, 7, 6) }));
+        assert_eq!(lexer.read_token(), Err(Error::End));
+    }
+
+    #[test]
+    fn unterminated_string() {
+        let source = "foo := 'Hello\n".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("foo".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Assign, None));
+        assert_eq!(lexer.read_token(), Err(Error::UnterminatedStringError { location: Location(1, 8, 7) }));
+        assert_eq!(lexer.read_token(), Err(Error::End));
+    }
+
+    #[test]
+    fn string_with_doubled_quote() {
+        let source = "'it''s' ''''".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::String, Some("it's".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::String, Some("'".to_string())));
+    }
+
+    #[test]
+    fn string_escapes() {
+        let source = r"'\t\b\n\r\f\0\'\\'".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::String, Some("\t\u{8}\n\r\u{c}\0'\\".to_string())));
+    }
+
+    #[test]
+    fn invalid_string_escape() {
+        let source = r"'a\qb' next".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token(), Err(Error::InvalidEscapeError {
+            text: r"\q".to_string(),
+            location: Location(1, 3, 2),
+        }));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("next".to_string())));
+    }
+
+    #[test]
+    fn escaped_strings_round_trip() {
+        let text = "tab\there 'quoted' back\\slash\n\0";
+        let escaped = escape_string(text);
+        assert_eq!(escaped, r"'tab\there \'quoted\' back\\slash\n\0'");
+        let mut lexer = Lexer::new(escaped.as_bytes());
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::String, Some(text.to_string())));
+    }
+
     #[test]
     fn identifier() {
         let source = "Hello".as_bytes();
@@ -353,6 +730,17 @@ mod tests {
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::KeywordSequence, Some("foo:bar:baz:".to_string())));
     }
 
+    #[test]
+    fn keyword_sequences_disabled() {
+        let options = ParserOptions { keyword_sequences: false, separator_length: 6, ..Default::default() };
+        let tokens: Vec<_> = SourceLexer::new("at:put: ------").with_options(options).map(|item| item.unwrap().0).collect();
+        assert_eq!(tokens, vec![
+            BorrowedToken(Symbol::Keyword, Some(Cow::Borrowed("at:"))),
+            BorrowedToken(Symbol::Keyword, Some(Cow::Borrowed("put:"))),
+            BorrowedToken(Symbol::Separator, None),
+        ]);
+    }
+
     #[test]
     fn primitive() {
         let source = "primitive".as_bytes();
@@ -384,6 +772,66 @@ mod tests {
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Minus, None));
     }
 
+    fn symbols_and_text(source: &str) -> Vec<(Symbol, String)> {
+        SourceLexer::new(source).map(|item| {
+            let BorrowedItem(token, _) = item.unwrap();
+            let token = token.into_owned();
+            (token.0.clone(), token.text())
+        }).collect()
+    }
+
+    #[test]
+    fn negative_literals_only_in_literal_position() {
+        let minus = || (Symbol::Minus, "-".to_string());
+        let integer = |text: &str| (Symbol::Integer, text.to_string());
+        let identifier = |text: &str| (Symbol::Identifier, text.to_string());
+
+        assert_eq!(symbols_and_text("-4"), vec![integer("-4")]);
+        assert_eq!(symbols_and_text("3 -4"), vec![integer("3"), minus(), integer("4")]);
+        assert_eq!(symbols_and_text("3-4"), vec![integer("3"), minus(), integer("4")]);
+        assert_eq!(symbols_and_text("x-1"), vec![identifier("x"), minus(), integer("1")]);
+        assert_eq!(symbols_and_text("a - -1"), vec![identifier("a"), minus(), integer("-1")]);
+        assert_eq!(symbols_and_text("a--1"), vec![identifier("a"), minus(), integer("-1")]);
+        assert_eq!(symbols_and_text("(1)-2"), vec![
+            (Symbol::NewTerm, "(".to_string()), integer("1"), (Symbol::EndTerm, ")".to_string()), minus(), integer("2"),
+        ]);
+        assert_eq!(symbols_and_text("- foo"), vec![minus(), identifier("foo")]);
+        assert_eq!(symbols_and_text("- 1"), vec![minus(), integer("1")]);
+    }
+
+    #[test]
+    fn negative_literal_forms() {
+        assert_eq!(symbols_and_text("^-3.5"), vec![(Symbol::Exit, "^".to_string()), (Symbol::Double, "-3.5".to_string())]);
+        assert_eq!(symbols_and_text("x := -16r1F"), vec![
+            (Symbol::Identifier, "x".to_string()), (Symbol::Assign, ":=".to_string()), (Symbol::Integer, "-31".to_string()),
+        ]);
+        assert_eq!(symbols_and_text("at: -1e2"), vec![(Symbol::Keyword, "at:".to_string()), (Symbol::Integer, "-100".to_string())]);
+    }
+
+    #[test]
+    fn minus_after_symbol_literals() {
+        for &source in &["#at: -1", "#at:put: -1", "#+ -1", "#foo -1", "#'foo' -1"] {
+            let symbols: Vec<_> = symbols_and_text(source).into_iter().map(|(symbol, _)| symbol).collect();
+            assert_eq!(symbols[2..], [Symbol::Minus, Symbol::Integer], "{}", source);
+        }
+    }
+
+    #[test]
+    fn negative_literals_in_literal_arrays() {
+        let minus = || (Symbol::Minus, "-".to_string());
+        let integer = |text: &str| (Symbol::Integer, text.to_string());
+        let pound = || (Symbol::Pound, "#".to_string());
+        let open = || (Symbol::NewTerm, "(".to_string());
+        let close = || (Symbol::EndTerm, ")".to_string());
+
+        assert_eq!(symbols_and_text("#(1 -2)"), vec![pound(), open(), integer("1"), integer("-2"), close()]);
+        assert_eq!(symbols_and_text("#(1 - 2)"), vec![pound(), open(), integer("1"), minus(), integer("2"), close()]);
+        assert_eq!(symbols_and_text("#(#(1) -2) -3"), vec![
+            pound(), open(), pound(), open(), integer("1"), close(), integer("-2"), close(), minus(), integer("3"),
+        ]);
+        assert_eq!(symbols_and_text("(1) -2"), vec![open(), integer("1"), close(), minus(), integer("2")]);
+    }
+
     #[test]
     fn separator() {
         let source = "-----".as_bytes();
@@ -421,6 +869,51 @@ mod tests {
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Double, Some("3.14".to_string())));
     }
 
+    #[test]
+    fn radix_integers() {
+        let source = "16r1F 2r1010 36rZz 16rFFFFFFFFFFFFFFFFFFFF".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Integer, Some("31".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Integer, Some("10".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Integer, Some("1295".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Integer, Some("1208925819614629174706175".to_string())));
+    }
+
+    #[test]
+    fn exponents() {
+        let source = "1e10 1.5e-3 2.5e2 1e-2".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Integer, Some("10000000000".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Double, Some("0.0015".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Double, Some("250.0".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Double, Some("0.01".to_string())));
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        let source = "16rZZ 1e 37r1 2e-x".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token(), Err(Error::InvalidNumberError { text: "16rZZ".to_string(), location: Location(1, 1, 0) }));
+        assert_eq!(lexer.read_token(), Err(Error::InvalidNumberError { text: "1e".to_string(), location: Location(1, 7, 6) }));
+        assert_eq!(lexer.read_token(), Err(Error::InvalidNumberError { text: "37r1".to_string(), location: Location(1, 10, 9) }));
+        assert_eq!(lexer.read_token(), Err(Error::InvalidNumberError { text: "2e-".to_string(), location: Location(1, 15, 14) }));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("x".to_string())));
+    }
+
+    #[test]
+    fn out_of_range_numbers() {
+        let long = format!("1{}.5", "0".repeat(400));
+        let source = format!("1e1024 1e1025 1e999999999 1.5e400 2.5e-1025 {} 1e-300", long);
+        let mut lexer = Lexer::new(source.as_bytes());
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Integer, Some(format!("1{}", "0".repeat(1024)))));
+        assert_eq!(lexer.read_token(), Err(Error::InvalidNumberError { text: "1e1025".to_string(), location: Location(1, 8, 7) }));
+        assert_eq!(lexer.read_token(), Err(Error::InvalidNumberError { text: "1e999999999".to_string(), location: Location(1, 15, 14) }));
+        assert_eq!(lexer.read_token(), Err(Error::InvalidNumberError { text: "1.5e400".to_string(), location: Location(1, 27, 26) }));
+        assert_eq!(lexer.read_token(), Err(Error::InvalidNumberError { text: "2.5e-1025".to_string(), location: Location(1, 35, 34) }));
+        assert_eq!(lexer.read_token(), Err(Error::InvalidNumberError { text: long.clone(), location: Location(1, 45, 44) }));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Double, Some("1e-300".to_string())));
+    }
+
     #[test]
     fn colon() {
         let source = ":".as_bytes();
@@ -439,7 +932,7 @@ mod tests {
 
     #[test]
     fn simple_symbols() {
-        let source = "[]()#^.".as_bytes();
+        let source = "[]()#^.;".as_bytes();
         let mut lexer = Lexer::new(source);
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::NewBlock, None));
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::EndBlock, None));
@@ -448,6 +941,17 @@ mod tests {
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Pound, None));
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Exit, None));
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Period, None));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Semicolon, None));
+    }
+
+    #[test]
+    fn character() {
+        let source = "$a $  $$".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Character, Some("a".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Character, Some(" ".to_string())));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Character, Some("$".to_string())));
+        assert_eq!(lexer.read_token(), Err(Error::End));
     }
 
     #[test]
@@ -476,12 +980,70 @@ mod tests {
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::OperatorSequence, Some("<=".to_string())));
     }
 
+    #[test]
+    fn names_are_interned() {
+        let mut lexer = Lexer::new("at: i put: i + 1".as_bytes());
+        let names: Vec<_> = lexer.by_ref().map(|item| item.unwrap().2).collect();
+        let interner = lexer.into_interner();
+        assert_eq!(names, vec![
+            interner.get("at:"),
+            interner.get("i"),
+            interner.get("put:"),
+            interner.get("i"),
+            interner.get("+"),
+            None,
+        ]);
+        assert_eq!(interner.len(), 4);
+    }
+
+    #[test]
+    fn unknown_character() {
+        let source = "a ? b".as_bytes();
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("a".to_string())));
+        assert_eq!(lexer.read_token(), Err(Error::UnknownCharacterError {
+            text: "?".to_string(),
+            location: Location(1, 3, 2),
+        }));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("b".to_string())));
+    }
+
+    #[test]
+    fn iterator_yields_errors() {
+        let source = "a $".as_bytes();
+        let lexer = Lexer::new(source);
+        let results: Vec<_> = lexer.map(|result| result.is_ok()).collect();
+        assert_eq!(results, vec![true, false]);
+    }
+
     #[test]
     fn location() {
         let source = " \n  World".as_bytes();
         let mut lexer = Lexer::new(source);
-        let Item(_, location) = lexer.read_token().unwrap();
-        assert_eq!(location, Location(2, 3));
+        let Item(_, span, _) = lexer.read_token().unwrap();
+        assert_eq!(span.start, Location(2, 3, 4));
+    }
+
+    #[test]
+    fn span() {
+        let source = " World\n".as_bytes();
+        let mut lexer = Lexer::new(source);
+        let Item(_, span, _) = lexer.read_token().unwrap();
+        assert_eq!(span, Span::new(Location(1, 2, 1), Location(1, 7, 6)));
+    }
+
+    #[test]
+    fn span_of_queued_tokens() {
+        let source = "1. --".as_bytes();
+        let mut lexer = Lexer::new(source);
+        let Item(_, span, _) = lexer.read_token().unwrap();
+        assert_eq!(span, Span::new(Location(1, 1, 0), Location(1, 2, 1)));
+        let Item(_, span, _) = lexer.read_token().unwrap();
+        assert_eq!(span, Span::new(Location(1, 2, 1), Location(1, 3, 2)));
+        let Item(_, span, _) = lexer.read_token().unwrap();
+        assert_eq!(span, Span::new(Location(1, 4, 3), Location(1, 5, 4)));
+        let Item(_, span, _) = lexer.read_token().unwrap();
+        assert_eq!(span, Span::new(Location(1, 5, 4), Location(1, 6, 5)));
     }
 
     #[test]
@@ -496,7 +1058,7 @@ mod tests {
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("Hello".to_string())));
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Equal, None));
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::NewTerm, None));
-        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some(("run".to_string()))));
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Identifier, Some("run".to_string())));
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Equal, None));
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::NewTerm, None));
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::String, Some("Hello, World from SOM".to_string())));
@@ -504,4 +1066,106 @@ mod tests {
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::EndTerm, None));
         assert_eq!(lexer.read_token().unwrap(), Token(Symbol::EndTerm, None));
     }
+
+    #[test]
+    fn source_lexer_borrows_text() {
+        let source = "foo at:put: 'bar' 42 ++";
+        let tokens: Vec<_> = SourceLexer::new(source).map(|item| item.unwrap().0).collect();
+        for token in &tokens {
+            match *token {
+                BorrowedToken(_, Some(Cow::Borrowed(_))) => {}
+                ref token => panic!("expected borrowed text: {:?}", token),
+            }
+        }
+
+        assert_eq!(tokens[2], BorrowedToken(Symbol::String, Some(Cow::Borrowed("bar"))));
+    }
+
+    #[test]
+    fn source_lexer_owns_unescaped_text() {
+        let source = "'it''s\\n' 16r1F";
+        let mut lexer = SourceLexer::new(source);
+        assert_eq!(lexer.read_token().unwrap().0, BorrowedToken(Symbol::String, Some(Cow::Owned("it's\n".to_string()))));
+        assert_eq!(lexer.read_token().unwrap().0, BorrowedToken(Symbol::Integer, Some(Cow::Owned("31".to_string()))));
+    }
+
+    #[test]
+    fn source_lexer_from_bytes() {
+        let mut lexer = SourceLexer::from_bytes(b"foo").unwrap();
+        assert_eq!(lexer.next(), Some(Ok(BorrowedItem(
+            BorrowedToken(Symbol::Identifier, Some(Cow::Borrowed("foo"))),
+            Span::new(Location(1, 1, 0), Location(1, 4, 3)),
+        ))));
+        assert!(SourceLexer::from_bytes(b"\xff").is_err());
+    }
+
+    #[test]
+    fn reader_lexer_reports_io_errors() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"))
+            }
+        }
+
+        let mut lexer = Lexer::new(BufReader::new(Failing));
+        assert_eq!(lexer.next(), Some(Err(Error::IoError { kind: io::ErrorKind::PermissionDenied, description: "denied".to_string() })));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn location_after_newline() {
+        let mut lexer = SourceLexer::new("a\nbc");
+        assert_eq!(lexer.read_token().unwrap().1, Span::new(Location(1, 1, 0), Location(1, 2, 1)));
+        assert_eq!(lexer.read_token().unwrap().1, Span::new(Location(2, 1, 2), Location(2, 3, 4)));
+    }
+
+    #[test]
+    fn location_counts_bytes() {
+        let mut lexer = SourceLexer::new("'\u{e9}'\nbc");
+        assert_eq!(lexer.read_token().unwrap().1, Span::new(Location(1, 1, 0), Location(1, 4, 4)));
+        assert_eq!(lexer.read_token().unwrap().1, Span::new(Location(2, 1, 5), Location(2, 3, 7)));
+    }
+
+    #[test]
+    fn invalid_utf8_is_reported() {
+        let source: &[u8] = b"a\n \xc3\xa9 \xff b";
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.next(), Some(Err(Error::InvalidUtf8Error { location: Location(2, 4, 6) })));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn trivia_tokens() {
+        let source = "a \"note\"\n\tb";
+        let tokens: Vec<_> = SourceLexer::new(source).with_trivia().map(|item| item.unwrap().0.into_owned()).collect();
+        assert_eq!(tokens, vec![
+            Token(Symbol::Identifier, Some("a".to_string())),
+            Token(Symbol::Whitespace, Some(" ".to_string())),
+            Token(Symbol::Comment, Some("\"note\"".to_string())),
+            Token(Symbol::Whitespace, Some("\n\t".to_string())),
+            Token(Symbol::Identifier, Some("b".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn trivia_spans_cover_source() {
+        let source = "\"Doc\" Hello = (\n    \"run\" run = ( 'x' println. ^ 16r1F )\n)\n";
+        let mut regenerated = String::new();
+        for item in SourceLexer::new(source).with_trivia() {
+            let BorrowedItem(_, span) = item.unwrap();
+            regenerated.push_str(&source[span.start.offset()..span.end.offset()]);
+        }
+
+        assert_eq!(regenerated, source);
+    }
+
+    #[test]
+    fn trivia_reports_unterminated_comment() {
+        let source = " \"open".as_bytes();
+        let mut lexer = Lexer::new(source).with_trivia();
+        assert_eq!(lexer.read_token().unwrap(), Token(Symbol::Whitespace, Some(" ".to_string())));
+        assert_eq!(lexer.read_token(), Err(Error::UnterminatedCommentError { location: Location(1, 2, 1) }));
+    }
 }
@@ -1,8 +1,21 @@
-pub use self::token::{Symbol, Token};
-pub use self::lexer::Lexer;
-pub use self::parser::Parser;
+pub use self::interner::{Interner, Name};
+pub use self::token::{BorrowedToken, Symbol, Token};
+pub use self::lexer::{Lexer, SourceLexer};
+pub use self::options::{Dialect, ParserOptions};
+pub use self::parser::{Error, Parser};
 
-mod ast;
-mod lexer;
-mod parser;
+pub mod ast;
+pub mod check;
+pub mod cst;
+pub mod grammar;
+pub mod incremental;
+pub mod interner;
+pub mod lexer;
+pub mod options;
+pub mod parser;
+pub mod printer;
+pub mod resolve;
+#[cfg(test)]
+pub mod test_util;
+pub mod visit;
 mod token;
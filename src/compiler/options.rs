@@ -0,0 +1,55 @@
+use compiler::ast::Superclass;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    #[default]
+    SomJava,
+    SomCpp,
+    TruffleSom,
+    SomNsLite,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParserOptions {
+    pub cascades: bool,
+    pub keyword_sequences: bool,
+    pub separator_length: usize,
+    pub string_escapes: bool,
+    pub default_superclass: Superclass,
+}
+
+impl Dialect {
+    pub fn options(self) -> ParserOptions {
+        let standard = ParserOptions {
+            cascades: false,
+            keyword_sequences: true,
+            separator_length: 4,
+            string_escapes: true,
+            default_superclass: Superclass::Implicit,
+        };
+
+        match self {
+            Dialect::SomJava | Dialect::TruffleSom => standard,
+            Dialect::SomCpp => ParserOptions { string_escapes: false, ..standard },
+            Dialect::SomNsLite => ParserOptions {
+                cascades: true,
+                keyword_sequences: false,
+                separator_length: 10,
+                default_superclass: Superclass::None,
+                ..standard
+            },
+        }
+    }
+}
+
+impl Default for ParserOptions {
+    fn default() -> ParserOptions {
+        Dialect::default().options()
+    }
+}
+
+impl From<Dialect> for ParserOptions {
+    fn from(dialect: Dialect) -> ParserOptions {
+        dialect.options()
+    }
+}
@@ -1,242 +1,396 @@
-use compiler::{ast, Lexer, Symbol, Token};
+use compiler::{ast, Lexer, ParserOptions, Symbol, Token};
+use compiler::ast::{Expression, ExpressionKind, Name};
+use compiler::interner::Interner;
+use compiler::grammar::{is_binary_operator, is_pattern_start, is_reserved_name, number_literal};
+use compiler::lexer;
 use compiler::lexer::Item;
-use util::peekable_buffer::Location;
+use util::location::{Location, Span};
 use std::collections::{HashMap, VecDeque};
 use std::io::BufRead;
-use std::iter::Peekable;
+use std::mem;
 use std::path::Path;
 
-const BINARY_OPERATORS: [Symbol; 14] = [
-    Symbol::And, Symbol::At, Symbol::Comma, Symbol::Divide, Symbol::Equal,
-    Symbol::Less, Symbol::Minus, Symbol::Modulus, Symbol::More, Symbol::Not,
-    Symbol::Or, Symbol::Percent, Symbol::Plus, Symbol::Star,
-];
-
-fn is_binary_operator(symbol: &Symbol) -> bool {
-    BINARY_OPERATORS.contains(symbol)
-}
-
-fn binary_symbol_to_string(symbol: &Symbol) -> String {
-    match symbol {
-        &Symbol::And     => "&",
-        &Symbol::At      => "@",
-        &Symbol::Comma   => ",",
-        &Symbol::Divide  => "/",
-        &Symbol::Equal   => "=",
-        &Symbol::Less    => "<",
-        &Symbol::Minus   => "-",
-        &Symbol::Modulus => "\\",
-        &Symbol::More    => ">",
-        &Symbol::Not     => "~",
-        &Symbol::Or      => "|",
-        &Symbol::Percent => "%",
-        &Symbol::Plus    => "+",
-        &Symbol::Star    => "*",
-        _                => unreachable!(),
-    }.to_string()
+fn split_message(expression: Expression) -> Option<(Expression, ast::Message)> {
+    match expression.kind {
+        ExpressionKind::UnaryMessage { message, receiver } => {
+            Some((*receiver, ast::Message { selector: message, arguments: vec![] }))
+        }
+        ExpressionKind::BinaryMessage { message, left, right } => {
+            Some((*left, ast::Message { selector: message, arguments: vec![*right] }))
+        }
+        ExpressionKind::KeywordMessage { message, receiver, parameters } => {
+            Some((*receiver, ast::Message { selector: message, arguments: parameters }))
+        }
+        _ => None,
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Error {
     ParseError { description: String, filename: String, line: usize, position: usize },
     MismatchError { expected: Vec<Symbol>, found: Symbol, location: Location },
+    LexicalError(lexer::Error),
+    UnexpectedTokenError { text: String, location: Location },
+    NumberError { text: String, location: Location },
+    DuplicateMethodError { name: Name, location: Location },
+    DuplicateVariableError { name: Name, location: Location },
+    ReservedNameError { name: Name, location: Location },
     End
 }
 
+impl Error {
+    fn position(&self) -> Option<(usize, usize)> {
+        match *self {
+            Error::ParseError { line, position, .. } => Some((line, position)),
+            Error::MismatchError { location, .. } |
+            Error::UnexpectedTokenError { location, .. } |
+            Error::NumberError { location, .. } |
+            Error::DuplicateMethodError { location, .. } |
+            Error::DuplicateVariableError { location, .. } |
+            Error::ReservedNameError { location, .. } => Some((location.line(), location.column())),
+            Error::LexicalError(ref error) => error.location().map(|location| (location.line(), location.column())),
+            Error::End => None,
+        }
+    }
+}
+
+impl From<lexer::Error> for Error {
+    fn from(error: lexer::Error) -> Error {
+        match error {
+            lexer::Error::End => Error::End,
+            error => Error::LexicalError(error),
+        }
+    }
+}
+
 pub struct Parser<R: BufRead, P: AsRef<Path>> {
-    lexer: Peekable<Lexer<R>>,
-    queue: VecDeque<Item>,
+    lexer: Lexer<R>,
+    options: ParserOptions,
+    queue: VecDeque<Result<Item, Error>>,
     filename: P,
+    last_end: Location,
+    recovering: bool,
+    diagnostics: Vec<Error>,
+    comments: Vec<String>,
+    leading_comments: HashMap<usize, Vec<String>>,
 }
 
 impl<R: BufRead, P: AsRef<Path>> Parser<R, P> {
     pub fn new(reader: R, filename: P) -> Parser<R, P> {
         Parser {
-            lexer: Lexer::new(reader).peekable(),
+            lexer: Lexer::new(reader),
+            options: ParserOptions::default(),
             queue: VecDeque::new(),
-            filename: filename,
+            filename,
+            last_end: Location::default(),
+            recovering: false,
+            diagnostics: vec![],
+            comments: vec![],
+            leading_comments: HashMap::new(),
         }
     }
 
+    pub fn with_trivia(self) -> Parser<R, P> {
+        Parser { lexer: self.lexer.with_trivia(), ..self }
+    }
+
+    pub fn with_options(self, options: ParserOptions) -> Parser<R, P> {
+        Parser { lexer: self.lexer.with_options(options.clone()), options, ..self }
+    }
+
+    pub fn with_interner(self, interner: Interner) -> Parser<R, P> {
+        Parser { lexer: self.lexer.with_interner(interner), ..self }
+    }
+
+    pub fn interner(&self) -> &Interner {
+        self.lexer.interner()
+    }
+
+    pub fn into_interner(self) -> Interner {
+        self.lexer.into_interner()
+    }
+
+    pub fn parse_class_with_recovery(&mut self) -> (Option<ast::Class>, Vec<Error>) {
+        self.recovering = true;
+        let result = self.parse_class();
+        self.recovering = false;
+
+        let class = match result {
+            Ok(class) => Some(class),
+            Err(error) => {
+                self.report(error);
+                None
+            }
+        };
+
+        (class, mem::take(&mut self.diagnostics))
+    }
+
+    pub fn parse_standalone_expression(&mut self) -> Result<Expression, Error> {
+        let result = self.parse_expression();
+        self.finish(result)
+    }
+
+    pub fn parse_statements(&mut self) -> Result<ast::Block, Error> {
+        let start = self.start_location();
+        let result = self.parse_locals().and_then(|locals| {
+            let body = self.parse_block_body()?;
+            Ok(ast::Block::new(vec![], vec![], locals, body, self.span_from(start)))
+        });
+
+        self.finish(result)
+    }
+
+    pub fn parse_standalone_method(&mut self) -> Result<ast::Method, Error> {
+        let result = self.parse_method();
+        self.finish(result)
+    }
+
     pub fn parse_class(&mut self) -> Result<ast::Class, Error> {
-        let name = try!(self.expect(Symbol::Identifier)).unwrap();
-        try!(self.expect(Symbol::Equal));
-        let superclass = try!(self.parse_superclass_name());
-        try!(self.expect(Symbol::NewTerm));
+        let result = self.parse_class_definition();
+        self.finish(result)
+    }
 
-        let instance_variables = try!(self.parse_locals());
-        let mut instance_methods = HashMap::new();
-        loop {
-            let (name, method) = match try!(self.peek(1)) {
-                Token(Symbol::Identifier, _) => try!(self.parse_method()),
-                Token(Symbol::Keyword, _) => try!(self.parse_method()),
-                Token(Symbol::OperatorSequence, _) => try!(self.parse_method()),
-                Token(ref symbol, _) if is_binary_operator(symbol) => try!(self.parse_method()),
-                _ => break,
-            };
+    fn parse_class_definition(&mut self) -> Result<ast::Class, Error> {
+        let start = self.start_location();
+        let comments = self.take_comments(start);
+        let name = self.expect_name(Symbol::Identifier)?;
+        self.expect(Symbol::Equal)?;
+        let superclass = self.parse_superclass()?;
+        self.expect(Symbol::NewTerm)?;
 
-            instance_methods.insert(name, method);
-        }
+        let instance_variables = self.parse_locals()?;
+        let instance_methods = self.parse_methods()?;
 
-        let mut class_methods = HashMap::new();
+        let mut class_methods = ast::MethodTable::new();
         let mut class_variables = vec![];
         if self.accept(Symbol::Separator).is_ok() {
-            class_variables = try!(self.parse_locals());
-            loop {
-                let (name, method) = match try!(self.peek(1)) {
-                    Token(Symbol::Identifier, _) => try!(self.parse_method()),
-                    Token(Symbol::Keyword, _) => try!(self.parse_method()),
-                    Token(Symbol::OperatorSequence, _) => try!(self.parse_method()),
-                    Token(ref symbol, _) if is_binary_operator(symbol) => try!(self.parse_method()),
-                    _ => break,
-                };
-
-                class_methods.insert(name, method);
-            }
+            class_variables = self.parse_locals()?;
+            class_methods = self.parse_methods()?;
         }
 
-        try!(self.expect(Symbol::EndTerm));
+        let result = self.expect(Symbol::EndTerm);
+        self.recover(result)?;
+        self.leading_comments.clear();
 
         Ok(ast::Class {
-            name: name,
-            superclass: superclass,
-            instance_methods: instance_methods,
-            instance_variables: instance_variables,
-            class_methods: class_methods,
-            class_variables: class_variables,
+            name,
+            superclass,
+            instance_methods,
+            instance_variables,
+            class_methods,
+            class_variables,
+            comments,
+            span: self.span_from(start),
         })
     }
 
-    fn parse_superclass_name(&mut self) -> Result<String, Error> {
-        match self.accept(Symbol::Identifier) {
-            Ok(Token(Symbol::Identifier, text)) => Ok(text.unwrap()),
-            Ok(_) => unreachable!(),
-            Err(Error::MismatchError { expected: _, found: _, location: _ }) => Ok("Object".to_string()),
+    fn parse_superclass(&mut self) -> Result<ast::Superclass, Error> {
+        match self.accept_name(Symbol::Identifier) {
+            Ok(name) if self.interner().resolve(name) == "nil" => Ok(ast::Superclass::None),
+            Ok(name) => Ok(ast::Superclass::Named(name)),
+            Err(Error::MismatchError { .. }) => Ok(self.options.default_superclass.clone()),
             Err(e) => Err(e),
         }
     }
 
-    fn parse_method(&mut self) -> Result<(String, ast::Method), Error> {
-        let (name, parameters) = try!(self.parse_pattern());
-        try!(self.expect(Symbol::Equal));
+    fn parse_methods(&mut self) -> Result<ast::MethodTable, Error> {
+        let mut methods = ast::MethodTable::new();
+        loop {
+            let peeked = self.peek(1);
+            let result = match self.recover(peeked)? {
+                Some(Token(ref symbol, _)) if is_pattern_start(symbol) => self.parse_method(),
+                Some(Token(Symbol::EndTerm, _)) | Some(Token(Symbol::Separator, _)) | None => break,
+                Some(_) if !self.recovering => break,
+                Some(_) => {
+                    let error = self.unexpected_token();
+                    self.advance();
+                    Err(error)
+                }
+            };
+
+            match self.recover(result)? {
+                Some(method) => {
+                    if let Err(method) = methods.insert(method) {
+                        self.diagnose(Error::DuplicateMethodError { name: method.name(), location: method.span().start })?;
+                    }
+                }
+                None => self.synchronize_method(0),
+            }
+        }
+
+        Ok(methods)
+    }
+
+    fn parse_method(&mut self) -> Result<ast::Method, Error> {
+        let start = self.start_location();
+        let comments = self.take_comments(start);
+        let (name, parameters, parameter_spans) = self.parse_pattern()?;
+        self.expect(Symbol::Equal)?;
 
         if self.accept(Symbol::Primitive).is_ok() {
-            let method = ast::Method::Primitive {
-                name: name.clone(),
-                parameters: parameters,
-            };
-            Ok((name, method))
+            Ok(ast::Method::Primitive {
+                name,
+                parameters,
+                parameter_spans,
+                comments,
+                span: self.span_from(start),
+            })
         } else {
-            try!(self.expect(Symbol::NewTerm));
-            let method = ast::Method::Native {
-                name: name.clone(),
-                parameters: parameters,
-                locals: try!(self.parse_locals()),
-                body: try!(self.parse_block_body()),
+            let body_start = self.start_location();
+            self.expect(Symbol::NewTerm)?;
+            let locals = self.parse_locals();
+            let locals = match self.recover(locals)? {
+                Some(locals) => locals,
+                None => {
+                    self.synchronize_statement();
+                    vec![]
+                }
             };
-            try!(self.expect(Symbol::EndTerm));
-            Ok((name, method))
+
+            let body = self.parse_block_body()?;
+            let result = self.expect(Symbol::EndTerm);
+            if self.recover(result)?.is_none() {
+                self.synchronize_method(1);
+            }
+
+            Ok(ast::Method::Native {
+                name,
+                body: ast::Block::new(parameters, parameter_spans, locals, body, self.span_from(body_start)),
+                comments,
+                span: self.span_from(start),
+            })
         }
     }
 
-    fn parse_pattern(&mut self) -> Result<(String, Vec<String>), Error> {
+    fn parse_pattern(&mut self) -> Result<(Name, Vec<Name>, Vec<Span>), Error> {
         match self.peek(1) {
             Ok(Token(Symbol::Identifier, _)) => self.parse_unary_pattern(),
             Ok(Token(Symbol::Keyword, _)) => self.parse_keyword_pattern(),
-            Ok(Token(Symbol::OperatorSequence, _)) => self.parse_binary_pattern(),
             Ok(Token(ref symbol, _)) if is_binary_operator(symbol) => self.parse_binary_pattern(),
-            _ => unreachable!(),
+            Ok(_) => Err(self.unexpected_token()),
+            Err(e) => Err(e),
         }
     }
 
-    fn parse_unary_pattern(&mut self) -> Result<(String, Vec<String>), Error> {
-        let name = try!(self.expect(Symbol::Identifier)).unwrap();
-        Ok((name, vec![]))
+    fn parse_unary_pattern(&mut self) -> Result<(Name, Vec<Name>, Vec<Span>), Error> {
+        let name = self.expect_name(Symbol::Identifier)?;
+        Ok((name, vec![], vec![]))
     }
 
-    fn parse_keyword_pattern(&mut self) -> Result<(String, Vec<String>), Error> {
-        let mut name = try!(self.expect(Symbol::Keyword)).unwrap();
+    fn parse_keyword_pattern(&mut self) -> Result<(Name, Vec<Name>, Vec<Span>), Error> {
+        let mut name = self.expect(Symbol::Keyword)?.unwrap();
         let mut parameters = vec![];
-        parameters.push(try!(self.expect(Symbol::Identifier)).unwrap());
+        let mut spans = vec![];
+        self.parse_variable(&mut parameters, &mut spans)?;
         loop {
             match self.accept(Symbol::Keyword) {
-                Ok(Token(Symbol::Keyword, text)) => {
+                Ok(Token(_, text)) => {
                     name.push_str(text.unwrap().as_ref());
-                    parameters.push(try!(self.expect(Symbol::Identifier)).unwrap());
+                    self.parse_variable(&mut parameters, &mut spans)?;
                 },
-                Ok(_) => unreachable!(),
-                Err(Error::MismatchError { expected: _, found: _, location: _ }) => break,
+                Err(Error::MismatchError { .. }) => break,
                 Err(e) => return Err(e),
             }
         }
 
-        Ok((name, parameters))
+        Ok((self.intern(&name), parameters, spans))
     }
 
-    fn parse_binary_pattern(&mut self) -> Result<(String, Vec<String>), Error> {
+    fn parse_binary_pattern(&mut self) -> Result<(Name, Vec<Name>, Vec<Span>), Error> {
         let name = match self.peek(1) {
-            Ok(Token(Symbol::OperatorSequence, text)) => text.unwrap(),
-            Ok(Token(ref symbol, _)) if is_binary_operator(symbol) => binary_symbol_to_string(symbol),
-            _ => unreachable!(),
+            Ok(Token(symbol, _)) if is_binary_operator(&symbol) => self.accept_name(symbol)?,
+            Ok(_) => return Err(self.unexpected_token()),
+            Err(e) => return Err(e),
         };
 
-        try!(self.consume(1));
-        let parameter = try!(self.expect(Symbol::Identifier)).unwrap();
+        let mut parameters = vec![];
+        let mut spans = vec![];
+        self.parse_variable(&mut parameters, &mut spans)?;
+
+        Ok((name, parameters, spans))
+    }
 
-        Ok((name, vec![parameter]))
+    fn parse_variable(&mut self, names: &mut Vec<Name>, spans: &mut Vec<Span>) -> Result<(), Error> {
+        let start = self.start_location();
+        names.push(self.expect_name(Symbol::Identifier)?);
+        spans.push(self.span_from(start));
+        Ok(())
     }
 
-    fn parse_locals(&mut self) -> Result<Vec<String>, Error> {
+    fn parse_locals(&mut self) -> Result<Vec<Name>, Error> {
         let mut locals = Vec::new();
         if self.accept(Symbol::Or).is_ok() {
             loop {
-                match self.accept(Symbol::Identifier) {
-                    Ok(Token(_, text)) => locals.push(text.unwrap()),
-                    _ => break,
+                let location = self.start_location();
+                let name = match self.accept_name(Symbol::Identifier) {
+                    Ok(name) => name,
+                    Err(_) => break,
+                };
+
+                if locals.contains(&name) {
+                    self.diagnose(Error::DuplicateVariableError { name, location })?;
+                    continue;
                 }
+
+                if is_reserved_name(self.interner().resolve(name)) {
+                    self.diagnose(Error::ReservedNameError { name, location })?;
+                }
+
+                locals.push(name);
             }
 
-            try!(self.expect(Symbol::Or));
+            self.expect(Symbol::Or)?;
         }
 
         Ok(locals)
     }
 
-    fn parse_block_parameters(&mut self) -> Result<Vec<String>, Error> {
+    fn parse_block_parameters(&mut self) -> Result<(Vec<Name>, Vec<Span>), Error> {
         let mut parameters = vec![];
-        loop {
-            if self.peek(1) == Ok(Token(Symbol::Colon, None)) {
-                try!(self.expect(Symbol::Colon));
-                let parameter = try!(self.expect(Symbol::Identifier)).unwrap();
-                parameters.push(parameter);
-            } else {
-                if !parameters.is_empty() {
-                    try!(self.expect(Symbol::Or));
-                }
+        let mut spans = vec![];
+        while self.peek(1) == Ok(Token(Symbol::Colon, None)) {
+            self.expect(Symbol::Colon)?;
+            self.parse_variable(&mut parameters, &mut spans)?;
+        }
 
-                break;
-            }
+        if !parameters.is_empty() {
+            self.expect(Symbol::Or)?;
         }
 
-        Ok(parameters)
+        Ok((parameters, spans))
     }
 
-    fn parse_block_body(&mut self) -> Result<Vec<ast::Expression>, Error> {
+    fn parse_block_body(&mut self) -> Result<Vec<Expression>, Error> {
         let mut statements = Vec::new();
 
         loop {
-            match self.peek(1) {
+            let start = self.start_location();
+            let result = match self.peek(1) {
                 Ok(Token(Symbol::EndTerm, _)) => break,
                 Ok(Token(Symbol::EndBlock, _)) => break,
-                Ok(Token(Symbol::Exit, _)) => statements.push(try!(self.parse_result())),
-                Ok(_) => statements.push(try!(self.parse_expression())),
+                Ok(Token(Symbol::Exit, _)) => self.parse_result(),
+                Ok(_) => self.parse_expression(),
                 Err(Error::End) => break,
-                Err(_) => unreachable!(),
+                Err(e) => Err(e),
             };
 
-            if self.accept(Symbol::Period).is_ok() {
-                continue;
-            } else {
+            match self.recover(result)? {
+                Some(statement) => statements.push(statement),
+                None => {
+                    self.synchronize_statement();
+                    statements.push(Expression::new(ExpressionKind::Error, self.span_from(start)));
+                }
+            }
+
+            if self.recovering && !self.at_statement_end() {
+                let error = self.unexpected_token();
+                self.report(error);
+                self.synchronize_statement();
+            }
+
+            if self.accept(Symbol::Period).is_err() {
                 break;
             }
         }
@@ -244,42 +398,40 @@ impl<R: BufRead, P: AsRef<Path>> Parser<R, P> {
         Ok(statements)
     }
 
-    fn parse_result(&mut self) -> Result<ast::Expression, Error> {
-        try!(self.expect(Symbol::Exit));
-        let statement = Box::new(try!(self.parse_expression()));
-        Ok(ast::Expression::Return(statement))
+    fn parse_result(&mut self) -> Result<Expression, Error> {
+        let start = self.start_location();
+        self.expect(Symbol::Exit)?;
+        let statement = Box::new(self.parse_expression()?);
+        Ok(Expression::new(ExpressionKind::Return(statement), self.span_from(start)))
     }
 
-    fn parse_assignments(&mut self) -> Result<Vec<String>, Error> {
+    fn parse_assignments(&mut self) -> Result<(Vec<Name>, Vec<Span>), Error> {
         let mut assignments = vec![];
+        let mut spans = vec![];
 
-        loop {
-            if self.peek(2) == Ok(Token(Symbol::Assign, None)) {
-                assignments.push(try!(self.expect(Symbol::Identifier)).unwrap());
-                try!(self.expect(Symbol::Assign));
-            } else {
-                break;
-            }
+        while self.peek(2) == Ok(Token(Symbol::Assign, None)) {
+            self.parse_variable(&mut assignments, &mut spans)?;
+            self.expect(Symbol::Assign)?;
         }
 
-        Ok(assignments)
+        Ok((assignments, spans))
     }
 
-    fn parse_expression(&mut self) -> Result<ast::Expression, Error> {
+    fn parse_expression(&mut self) -> Result<Expression, Error> {
         if self.peek(2) == Ok(Token(Symbol::Assign, None)) {
-            Ok(ast::Expression::Assignment {
-                variables: try!(self.parse_assignments()),
-                value: Box::new(try!(self.parse_expression())),
-            })
+            let start = self.start_location();
+            let (variables, variable_spans) = self.parse_assignments()?;
+            let value = Box::new(self.parse_expression()?);
+            Ok(Expression::new(ExpressionKind::Assignment { variables, variable_spans, value }, self.span_from(start)))
         } else {
-            let mut expression = try!(self.parse_expression_primary());
+            let mut expression = self.parse_expression_primary()?;
 
             loop {
                 expression = match self.peek(1) {
-                    Ok(Token(Symbol::Identifier, _)) => try!(self.parse_expression_messages(expression)),
-                    Ok(Token(Symbol::Keyword, _)) => try!(self.parse_expression_messages(expression)),
-                    Ok(Token(Symbol::OperatorSequence, _)) => try!(self.parse_expression_messages(expression)),
-                    Ok(Token(ref symbol, _)) if is_binary_operator(symbol) => try!(self.parse_expression_messages(expression)),
+                    Ok(Token(Symbol::Identifier, _)) => self.parse_expression_messages(expression)?,
+                    Ok(Token(Symbol::Keyword, _)) => self.parse_expression_messages(expression)?,
+                    Ok(Token(ref symbol, _)) if is_binary_operator(symbol) => self.parse_expression_messages(expression)?,
+                    Ok(Token(Symbol::Semicolon, _)) if self.options.cascades => return self.parse_expression_cascade(expression),
                     _ => break,
                 }
             }
@@ -288,162 +440,229 @@ impl<R: BufRead, P: AsRef<Path>> Parser<R, P> {
         }
     }
 
-    fn parse_expression_primary(&mut self) -> Result<ast::Expression, Error> {
+    fn parse_expression_cascade(&mut self, expression: Expression) -> Result<Expression, Error> {
+        let span = expression.span;
+        let (receiver, first) = match split_message(expression) {
+            Some(split) => split,
+            None => return Err(self.unexpected_token()),
+        };
+
+        let mut messages = vec![first];
+        while self.accept(Symbol::Semicolon).is_ok() {
+            let start = self.start_location();
+            let placeholder = Expression::new(ExpressionKind::Error, Span::new(start, start));
+            let message = match self.peek(1)? {
+                Token(Symbol::Identifier, _) => self.parse_expression_unary_message(placeholder)?,
+                Token(Symbol::Keyword, _) => self.parse_expression_keyword_message(placeholder)?,
+                Token(ref symbol, _) if is_binary_operator(symbol) => self.parse_expression_binary_message(placeholder)?,
+                _ => return Err(self.unexpected_token()),
+            };
+
+            messages.extend(split_message(message).map(|(_, message)| message));
+        }
+
+        let kind = ExpressionKind::Cascade { receiver: Box::new(receiver), messages };
+        Ok(Expression::new(kind, self.span_from(span.start)))
+    }
+
+    fn parse_expression_primary(&mut self) -> Result<Expression, Error> {
         match self.peek(1) {
             Ok(Token(Symbol::Identifier, _)) => self.parse_expression_variable(),
             Ok(Token(Symbol::String, _)) => self.parse_expression_string(),
-            Ok(Token(Symbol::Integer, _)) => self.parse_expression_number(false),
-            Ok(Token(Symbol::Double, _)) => self.parse_expression_number(false),
+            Ok(Token(Symbol::Integer, _)) => self.parse_expression_number(),
+            Ok(Token(Symbol::Double, _)) => self.parse_expression_number(),
             Ok(Token(Symbol::Pound, _)) => self.parse_expression_symbol(),
-            Ok(Token(Symbol::Minus, _)) => self.parse_expression_negative_number(),
+            Ok(Token(Symbol::Character, _)) => self.parse_expression_character(),
             Ok(Token(Symbol::NewBlock, _)) => self.parse_expression_nested_block(),
             Ok(Token(Symbol::NewTerm, _)) => self.parse_expression_nested_term(),
-            Ok(t) => unreachable!(format!("token: {:#?}", t)),
+            Ok(_) => Err(self.unexpected_token()),
             Err(e) => Err(e),
         }
     }
 
-    fn parse_expression_messages(&mut self, value: ast::Expression) -> Result<ast::Expression, Error> {
+    fn parse_expression_messages(&mut self, value: Expression) -> Result<Expression, Error> {
         let mut expression = value;
-        let Token(symbol, _) = try!(self.peek(1));
+        let Token(symbol, _) = self.peek(1)?;
         match symbol {
             Symbol::Identifier => {
-                loop {
-                    if let Ok(Token(Symbol::Identifier, _)) = self.peek(1) {
-                        expression = try!(self.parse_expression_unary_message(expression));
-                    } else {
-                        break;
-                    }
+                while let Ok(Token(Symbol::Identifier, _)) = self.peek(1) {
+                    expression = self.parse_expression_unary_message(expression)?;
                 }
 
                 Ok(expression)
             }
             Symbol::Keyword => self.parse_expression_keyword_message(expression),
-            Symbol::OperatorSequence => self.parse_expression_binary_message(expression),
             ref s if is_binary_operator(s) => self.parse_expression_binary_message(expression),
-            _ => unreachable!(),
+            _ => Err(self.unexpected_token()),
         }
     }
 
-    fn parse_expression_nested_block(&mut self) -> Result<ast::Expression, Error> {
-        try!(self.expect(Symbol::NewBlock));
-        let value = ast::Expression::Block {
-            parameters: try!(self.parse_block_parameters()),
-            locals: try!(self.parse_locals()),
-            body: try!(self.parse_block_body()),
-        };
-        try!(self.expect(Symbol::EndBlock));
+    fn parse_expression_nested_block(&mut self) -> Result<Expression, Error> {
+        let start = self.start_location();
+        self.expect(Symbol::NewBlock)?;
+        let (parameters, parameter_spans) = self.parse_block_parameters()?;
+        let locals = self.parse_locals()?;
+        let body = self.parse_block_body()?;
+        self.expect(Symbol::EndBlock)?;
 
-        Ok(value)
+        let span = self.span_from(start);
+        Ok(Expression::new(ExpressionKind::Block(ast::Block::new(parameters, parameter_spans, locals, body, span)), span))
     }
 
-    fn parse_expression_nested_term(&mut self) -> Result<ast::Expression, Error> {
-        try!(self.expect(Symbol::NewTerm));
-        let value = try!(self.parse_expression());
-        try!(self.expect(Symbol::EndTerm));
+    fn parse_expression_nested_term(&mut self) -> Result<Expression, Error> {
+        let start = self.start_location();
+        self.expect(Symbol::NewTerm)?;
+        let mut value = self.parse_expression()?;
+        self.expect(Symbol::EndTerm)?;
+        value.span = self.span_from(start);
 
         Ok(value)
     }
 
-    fn parse_expression_variable(&mut self) -> Result<ast::Expression, Error> {
-        let variable = try!(self.expect(Symbol::Identifier)).unwrap();
-        let value = match variable.as_ref() {
-            "nil" => ast::Expression::LiteralNil,
-            "true" => ast::Expression::LiteralBoolean(true),
-            "false" => ast::Expression::LiteralBoolean(false),
-            _ => ast::Expression::Variable(variable),
+    fn parse_expression_variable(&mut self) -> Result<Expression, Error> {
+        let start = self.start_location();
+        let name = self.expect_name(Symbol::Identifier)?;
+        let kind = match self.interner().resolve(name) {
+            "nil" => ExpressionKind::LiteralNil,
+            "true" => ExpressionKind::LiteralBoolean(true),
+            "false" => ExpressionKind::LiteralBoolean(false),
+            _ => ExpressionKind::Variable(name),
         };
 
-        Ok(value)
+        Ok(Expression::new(kind, self.span_from(start)))
+    }
+
+    fn parse_expression_string(&mut self) -> Result<Expression, Error> {
+        let start = self.start_location();
+        let value = self.expect(Symbol::String)?.unwrap();
+        Ok(Expression::new(ExpressionKind::LiteralString(value), self.span_from(start)))
     }
 
-    fn parse_expression_string(&mut self) -> Result<ast::Expression, Error> {
-        let value = try!(self.expect(Symbol::String)).unwrap();
-        Ok(ast::Expression::LiteralString(value))
+    fn parse_expression_character(&mut self) -> Result<Expression, Error> {
+        let start = self.start_location();
+        let value = self.expect(Symbol::Character)?.unwrap();
+        let kind = ExpressionKind::LiteralCharacter(value.chars().next().unwrap());
+        Ok(Expression::new(kind, self.span_from(start)))
     }
 
-    fn parse_expression_symbol(&mut self) -> Result<ast::Expression, Error> {
-        try!(self.expect(Symbol::Pound));
+    fn parse_expression_symbol(&mut self) -> Result<Expression, Error> {
+        let start = self.start_location();
+        self.expect(Symbol::Pound)?;
+
+        if let Ok(Token(Symbol::NewTerm, _)) = self.peek(1) {
+            return self.parse_expression_literal_array(start);
+        }
 
         let value = match self.peek(1) {
-            Ok(Token(Symbol::Identifier, text)) => text.unwrap(),
-            Ok(Token(Symbol::String, text)) => text.unwrap(),
-            Ok(Token(Symbol::Keyword, text)) => text.unwrap(),
-            Ok(Token(Symbol::KeywordSequence, text)) => text.unwrap(),
-            Ok(Token(Symbol::OperatorSequence, text)) => text.unwrap(),
-            Ok(Token(ref symbol, _)) if is_binary_operator(symbol) => binary_symbol_to_string(symbol),
-            _ => unreachable!(),
+            Ok(Token(Symbol::String, text)) => {
+                self.consume(1)?;
+                self.intern(&text.unwrap_or_default())
+            }
+            Ok(Token(Symbol::Keyword, _)) => self.parse_keyword_symbol()?,
+            Ok(Token(symbol, _)) if matches!(symbol, Symbol::Identifier | Symbol::KeywordSequence) || is_binary_operator(&symbol) => {
+                self.accept_name(symbol)?
+            }
+            Ok(_) => return Err(self.unexpected_token()),
+            Err(e) => return Err(e),
         };
 
-        try!(self.consume(1));
-
-        Ok(ast::Expression::LiteralSymbol(value))
+        Ok(Expression::new(ExpressionKind::LiteralSymbol(value), self.span_from(start)))
     }
 
-    fn parse_expression_negative_number(&mut self) -> Result<ast::Expression, Error> {
-        try!(self.expect(Symbol::Minus));
-        self.parse_expression_number(true)
-    }
+    fn parse_expression_literal_array(&mut self, start: Location) -> Result<Expression, Error> {
+        self.expect(Symbol::NewTerm)?;
 
-    fn parse_expression_number(&mut self, negative: bool) -> Result<ast::Expression, Error> {
-        match self.accept_one_of(&[Symbol::Integer, Symbol::Double]) {
-            Ok(Token(Symbol::Integer, Some(text))) => {
-                let mut value: i64 = text.parse().unwrap();
-                if negative {
-                    value = -value;
+        let mut values = vec![];
+        loop {
+            let value = match self.peek(1)? {
+                Token(Symbol::EndTerm, _) => break,
+                Token(Symbol::Integer, _) | Token(Symbol::Double, _) => self.parse_expression_number()?,
+                Token(Symbol::String, _) => self.parse_expression_string()?,
+                Token(Symbol::Character, _) => self.parse_expression_character()?,
+                Token(Symbol::Pound, _) => self.parse_expression_symbol()?,
+                Token(Symbol::NewTerm, _) => {
+                    let start = self.start_location();
+                    self.parse_expression_literal_array(start)?
                 }
+                Token(Symbol::Identifier, _) => match self.parse_expression_variable()? {
+                    Expression { kind: ExpressionKind::Variable(name), span } => {
+                        Expression::new(ExpressionKind::LiteralSymbol(name), span)
+                    }
+                    value => value,
+                },
+                _ => self.parse_literal_array_symbol()?,
+            };
 
-                Ok(ast::Expression::LiteralInteger(value))
-            },
-            Ok(Token(Symbol::Double, Some(text))) => {
-                let mut value: f64 = text.parse().unwrap();
-                if negative {
-                    value = -value;
-                }
+            values.push(value);
+        }
 
-                Ok(ast::Expression::LiteralDouble(value))
-            },
-            Ok(_) => unreachable!(),
-            Err(e) => Err(e),
+        self.expect(Symbol::EndTerm)?;
+
+        Ok(Expression::new(ExpressionKind::LiteralArray(values), self.span_from(start)))
+    }
+
+    fn parse_keyword_symbol(&mut self) -> Result<Name, Error> {
+        let mut value = self.expect(Symbol::Keyword)?.unwrap();
+        while self.peek(1).map(|Token(symbol, _)| symbol) == Ok(Symbol::Keyword) && self.start_location() == self.last_end {
+            value.push_str(&self.expect(Symbol::Keyword)?.unwrap());
         }
+
+        Ok(self.intern(&value))
     }
 
-    fn parse_expression_unary_message(&mut self, value: ast::Expression) -> Result<ast::Expression, Error> {
-        let message = try!(self.expect(Symbol::Identifier)).unwrap();
-        Ok(ast::Expression::UnaryMessage { receiver: Box::new(value), message: message })
+    fn parse_literal_array_symbol(&mut self) -> Result<Expression, Error> {
+        let start = self.start_location();
+        let value = match self.peek(1)? {
+            Token(Symbol::Keyword, _) => self.parse_keyword_symbol()?,
+            Token(symbol, _) if symbol == Symbol::KeywordSequence || is_binary_operator(&symbol) => self.accept_name(symbol)?,
+            _ => return Err(self.unexpected_token()),
+        };
+
+        Ok(Expression::new(ExpressionKind::LiteralSymbol(value), self.span_from(start)))
+    }
+
+    fn parse_expression_number(&mut self) -> Result<Expression, Error> {
+        let start = self.start_location();
+        let token = self.accept_one_of(&[Symbol::Integer, Symbol::Double])?;
+        let kind = match number_literal(&token) {
+            Some(kind) => kind,
+            None => return Err(Error::NumberError { text: token.text(), location: start }),
+        };
+
+        Ok(Expression::new(kind, self.span_from(start)))
+    }
+
+    fn parse_expression_unary_message(&mut self, value: Expression) -> Result<Expression, Error> {
+        let start = value.span.start;
+        let message = self.expect_name(Symbol::Identifier)?;
+        let kind = ExpressionKind::UnaryMessage { receiver: Box::new(value), message };
+        Ok(Expression::new(kind, self.span_from(start)))
     }
 
-    fn parse_expression_keyword_message(&mut self, value: ast::Expression) -> Result<ast::Expression, Error> {
+    fn parse_expression_keyword_message(&mut self, value: Expression) -> Result<Expression, Error> {
+        let start = value.span.start;
         let mut message = String::new();
         let mut parameters = Vec::new();
-        loop {
-            match self.accept(Symbol::Keyword) {
-                Ok(Token(Symbol::Keyword, text)) => {
-                    message.push_str(text.unwrap().as_ref());
-                    parameters.push(try!(self.parse_expression_formula()));
-                }
-                Ok(_) => unreachable!(),
-                Err(_) => break,
-            };
+        while let Ok(Token(_, text)) = self.accept(Symbol::Keyword) {
+            message.push_str(text.unwrap().as_ref());
+            parameters.push(self.parse_expression_formula()?);
         }
 
-        Ok(ast::Expression::KeywordMessage {
+        let kind = ExpressionKind::KeywordMessage {
             receiver: Box::new(value),
-            message: message,
-            parameters: parameters,
-        })
+            message: self.intern(&message),
+            parameters,
+        };
+        Ok(Expression::new(kind, self.span_from(start)))
     }
 
-    fn parse_expression_formula(&mut self) -> Result<ast::Expression, Error> {
-        let mut value = try!(self.parse_expression_binary_operand());
+    fn parse_expression_formula(&mut self) -> Result<Expression, Error> {
+        let mut value = self.parse_expression_binary_operand()?;
 
         loop {
             match self.peek(1) {
                 Ok(Token(ref symbol, _)) if is_binary_operator(symbol) => {
-                    value = try!(self.parse_expression_binary_message(value));
-                }
-                Ok(Token(Symbol::OperatorSequence, _)) => {
-                    value = try!(self.parse_expression_binary_message(value));
+                    value = self.parse_expression_binary_message(value)?;
                 }
                 _ => break,
             }
@@ -452,123 +671,315 @@ impl<R: BufRead, P: AsRef<Path>> Parser<R, P> {
         Ok(value)
     }
 
-    fn parse_expression_binary_operand(&mut self) -> Result<ast::Expression, Error> {
-        let mut value = try!(self.parse_expression_primary());
+    fn parse_expression_binary_operand(&mut self) -> Result<Expression, Error> {
+        let mut value = self.parse_expression_primary()?;
 
-        loop {
-            match self.peek(1) {
-                Ok(Token(Symbol::Identifier, _)) => {
-                    value = try!(self.parse_expression_unary_message(value));
-                }
-                _ => break,
-            }
+        while let Ok(Token(Symbol::Identifier, _)) = self.peek(1) {
+            value = self.parse_expression_unary_message(value)?;
         }
 
         Ok(value)
     }
 
-    fn parse_expression_binary_message(&mut self, value: ast::Expression) -> Result<ast::Expression, Error> {
+    fn parse_expression_binary_message(&mut self, value: Expression) -> Result<Expression, Error> {
+        let start = value.span.start;
         let message = match self.peek(1) {
-            Ok(Token(ref symbol, _)) if is_binary_operator(symbol) => binary_symbol_to_string(symbol),
-            Ok(Token(Symbol::OperatorSequence, text)) => text.unwrap(),
-            Ok(_) => unreachable!(),
+            Ok(Token(symbol, _)) if is_binary_operator(&symbol) => self.accept_name(symbol)?,
+            Ok(_) => return Err(self.unexpected_token()),
             Err(e) => return Err(e),
         };
 
-        try!(self.consume(1));
-
-        Ok(ast::Expression::BinaryMessage {
-            message: message,
+        let kind = ExpressionKind::BinaryMessage {
+            message,
             left: Box::new(value),
-            right: Box::new(try!(self.parse_expression_binary_operand())),
-        })
+            right: Box::new(self.parse_expression_binary_operand()?),
+        };
+        Ok(Expression::new(kind, self.span_from(start)))
     }
 
-    fn peek(&mut self, n: usize) -> Result<Token, Error> {
-        for _ in (self.queue.len()..n) {
+    fn fill(&mut self, n: usize) -> Result<(), Error> {
+        while self.queue.len() < n {
             match self.lexer.next() {
-                Some(t) => self.queue.push_back(t),
+                Some(Ok(Item(Token(Symbol::Comment, Some(text)), _, _))) => {
+                    self.comments.push(text[1..text.len() - 1].to_string());
+                }
+                Some(Ok(Item(Token(Symbol::Whitespace, _), _, _))) => {}
+                Some(result) => {
+                    if let Ok(Item(_, span, _)) = result {
+                        if !self.comments.is_empty() {
+                            self.leading_comments.insert(span.start.offset(), mem::take(&mut self.comments));
+                        }
+                    }
+
+                    self.queue.push_back(result.map_err(From::from));
+                }
                 None => return Err(Error::End),
             }
         }
 
-        match self.queue.get(n-1) {
-            Some(t) => Ok(t.0.clone()),
-            None => Err(Error::End),
+        Ok(())
+    }
+
+    fn take_comments(&mut self, start: Location) -> Vec<String> {
+        let comments = self.leading_comments.remove(&start.offset()).unwrap_or_default();
+        self.leading_comments.retain(|&offset, _| offset > start.offset());
+        comments
+    }
+
+    fn peek(&mut self, n: usize) -> Result<Token, Error> {
+        self.fill(n)?;
+        match self.queue[n - 1] {
+            Ok(Item(ref token, _, _)) => Ok(token.clone()),
+            Err(ref error) => Err(error.clone()),
+        }
+    }
+
+    fn start_location(&mut self) -> Location {
+        match self.fill(1).map(|_| &self.queue[0]) {
+            Ok(Ok(Item(_, span, _))) => span.start,
+            _ => self.last_end,
+        }
+    }
+
+    fn unexpected_token(&mut self) -> Error {
+        match self.fill(1).map(|_| &self.queue[0]) {
+            Ok(Ok(Item(token, span, _))) => Error::UnexpectedTokenError { text: token.text(), location: span.start },
+            Ok(Err(error)) => error.clone(),
+            Err(error) => error,
+        }
+    }
+
+    fn span_from(&self, start: Location) -> Span {
+        if self.last_end.offset() < start.offset() {
+            Span::new(start, start)
+        } else {
+            Span::new(start, self.last_end)
         }
     }
 
     fn consume(&mut self, n: usize) -> Result<(), Error> {
-        for _ in (0..n) {
-            if self.queue.is_empty() {
-                self.lexer.next();
-            } else {
-                self.queue.pop_front();
-            };
+        for _ in 0..n {
+            self.fill(1)?;
+            self.advance();
         }
 
         Ok(())
     }
 
+    fn advance(&mut self) {
+        if let Some(Ok(Item(_, span, _))) = self.queue.pop_front() {
+            self.last_end = span.end;
+        }
+    }
+
     fn accept(&mut self, expected: Symbol) -> Result<Token, Error> {
         self.accept_one_of(&[expected])
     }
 
     fn accept_one_of(&mut self, expected: &[Symbol]) -> Result<Token, Error> {
-        let result = {
-            let next_item = if self.queue.is_empty() {
-                self.lexer.peek()
-            } else {
-                self.queue.front()
-            };
+        self.accept_item(expected).map(|Item(token, _, _)| token)
+    }
 
-            match next_item {
-                Some(&Item(Token(ref symbol, ref text), ref location)) => {
-                    if expected.contains(&symbol) {
-                        Ok(Token(symbol.clone(), text.clone()))
-                    } else {
-                        Err(Error::MismatchError { expected: expected.to_owned(), found: symbol.clone(), location: *location })
-                    }
-                }
-                None => Err(Error::End),
+    fn accept_name(&mut self, expected: Symbol) -> Result<Name, Error> {
+        let Item(token, _, name) = self.accept_item(&[expected])?;
+        Ok(name.unwrap_or_else(|| self.intern(&token.text())))
+    }
+
+    fn accept_item(&mut self, expected: &[Symbol]) -> Result<Item, Error> {
+        self.fill(1)?;
+        let item = match self.queue[0] {
+            Ok(Item(ref token, span, _)) if !expected.contains(&token.0) => {
+                return Err(Error::MismatchError { expected: expected.to_owned(), found: token.0.clone(), location: span.start });
             }
+            Ok(ref item) => item.clone(),
+            Err(ref error) => return Err(error.clone()),
         };
 
-        if result.is_ok() {
-            if self.queue.is_empty() {
-                self.lexer.next();
-            } else {
-                self.queue.pop_front();
-            };
-        }
-
-        result
+        self.advance();
+        Ok(item)
     }
 
     fn expect(&mut self, expected: Symbol) -> Result<Option<String>, Error> {
         self.expect_one_of(&[expected])
     }
 
+    fn expect_name(&mut self, expected: Symbol) -> Result<Name, Error> {
+        let result = self.accept_name(expected);
+        self.expected(result)
+    }
+
+    fn intern(&mut self, text: &str) -> Name {
+        self.lexer.intern(text)
+    }
+
     fn expect_one_of(&mut self, expected: &[Symbol]) -> Result<Option<String>, Error> {
-        match self.accept_one_of(expected) {
-            Ok(Token(_, text)) => Ok(text),
-            Err(Error::MismatchError { expected, found, location }) => Err(Error::ParseError {
-                description: format!("Expected {:?}, found {:?}", expected, found),
-                filename: self.filename.as_ref().to_string_lossy().into_owned(),
-                line: location.0,
-                position: location.1,
-            }),
-            Err(e) => Err(e),
+        let result = self.accept_one_of(expected).map(|Token(_, text)| text);
+        self.expected(result)
+    }
+
+    fn expected<T>(&self, result: Result<T, Error>) -> Result<T, Error> {
+        match result {
+            Err(Error::MismatchError { expected, found, location }) => {
+                Err(self.parse_error(format!("Expected {:?}, found {:?}", expected, found), location))
+            }
+            result => result,
+        }
+    }
+
+    fn finish<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
+        let value = match result {
+            Err(Error::End) => return Err(self.parse_error("Unexpected end of input".to_string(), self.last_end)),
+            result => result?,
+        };
+
+        let trailing = match self.peek(1) {
+            Err(Error::End) => return Ok(value),
+            Err(error) => error,
+            Ok(_) => self.unexpected_token(),
+        };
+
+        self.diagnose(trailing)?;
+        Ok(value)
+    }
+
+    fn parse_error(&self, description: String, location: Location) -> Error {
+        Error::ParseError {
+            description,
+            filename: self.filename.as_ref().to_string_lossy().into_owned(),
+            line: location.line(),
+            position: location.column(),
+        }
+    }
+
+    fn recover<T>(&mut self, result: Result<T, Error>) -> Result<Option<T>, Error> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if self.recovering => {
+                self.report(error);
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn diagnose(&mut self, error: Error) -> Result<(), Error> {
+        self.recover(Err(error)).map(|_: Option<()>| ())
+    }
+
+    fn report(&mut self, error: Error) {
+        let error = match error {
+            Error::End => self.parse_error("Unexpected end of input".to_string(), self.last_end),
+            Error::MismatchError { expected, found, location } => {
+                self.parse_error(format!("Expected {:?}, found {:?}", expected, found), location)
+            }
+            error => error,
+        };
+
+        let duplicate = match self.diagnostics.last() {
+            Some(last) => last.position().is_some() && last.position() == error.position(),
+            None => false,
+        };
+
+        if !duplicate {
+            self.diagnostics.push(error);
+        }
+    }
+
+    fn synchronize_statement(&mut self) {
+        let mut depth = 0;
+        while let Some(symbol) = self.peek_symbol() {
+            match symbol {
+                Symbol::Period | Symbol::EndTerm | Symbol::EndBlock if depth == 0 => return,
+                Symbol::EndTerm | Symbol::EndBlock => depth -= 1,
+                Symbol::NewTerm | Symbol::NewBlock => depth += 1,
+                _ if depth == 0 && self.at_method_definition() => return,
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+
+    fn synchronize_method(&mut self, mut depth: usize) {
+        while let Some(symbol) = self.peek_symbol() {
+            match symbol {
+                Symbol::EndTerm | Symbol::Separator if depth == 0 => return,
+                Symbol::EndTerm if depth == 1 => {
+                    self.advance();
+                    return;
+                }
+                Symbol::EndTerm | Symbol::EndBlock if depth > 1 => depth -= 1,
+                Symbol::NewTerm | Symbol::NewBlock => depth += 1,
+                _ if depth <= 1 && self.at_method_definition() => return,
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+
+    fn peek_symbol(&mut self) -> Option<Symbol> {
+        match self.peek(1) {
+            Ok(Token(symbol, _)) => Some(symbol),
+            Err(Error::End) => None,
+            Err(_) => Some(Symbol::None),
+        }
+    }
+
+    fn at_statement_end(&mut self) -> bool {
+        match self.peek(1) {
+            Ok(Token(Symbol::Period, _)) | Ok(Token(Symbol::EndTerm, _)) | Ok(Token(Symbol::EndBlock, _)) => true,
+            Err(Error::End) => true,
+            _ => self.at_method_definition(),
+        }
+    }
+
+    fn at_method_definition(&mut self) -> bool {
+        let mut n = match self.peek(1) {
+            Ok(Token(Symbol::Identifier, _)) => 2,
+            Ok(Token(Symbol::Keyword, _)) => {
+                let mut n = 1;
+                while self.peek(n).map(|Token(symbol, _)| symbol) == Ok(Symbol::Keyword) &&
+                      self.peek(n + 1).map(|Token(symbol, _)| symbol) == Ok(Symbol::Identifier) {
+                    n += 2;
+                }
+
+                n
+            }
+            Ok(Token(ref symbol, _)) if is_pattern_start(symbol) => {
+                if self.peek(2).map(|Token(symbol, _)| symbol) != Ok(Symbol::Identifier) {
+                    return false;
+                }
+
+                3
+            }
+            _ => return false,
+        };
+
+        if n == 1 || self.peek(n).map(|Token(symbol, _)| symbol) != Ok(Symbol::Equal) {
+            return false;
         }
+
+        n += 1;
+        matches!(self.peek(n), Ok(Token(Symbol::NewTerm, _)) | Ok(Token(Symbol::Primitive, _)))
     }
 }
 
 #[cfg(test)]
-#[allow(unused_must_use)]
+#[allow(unused_must_use, clippy::approx_constant)]
 mod tests {
     use compiler::ast;
-    use compiler::Symbol;
+    use compiler::{lexer, Symbol};
+    use num_bigint::BigInt;
+    use compiler::ast::{ExpressionKind, Location, Span};
+    use compiler::{Dialect, ParserOptions};
     use super::{Error, Parser};
+    use std::io::BufRead;
+    use std::path::Path;
+
+    fn name<R: BufRead, P: AsRef<Path>>(parser: &Parser<R, P>, text: &str) -> ast::Name {
+        parser.interner().get(text).unwrap()
+    }
 
     #[test]
     fn parse_error() {
@@ -600,8 +1011,14 @@ mod tests {
     fn parse_method_primitive() {
         let source = "hello = primitive".as_bytes();
         let mut parser = Parser::new(source, "test");
-        let (_, method) = parser.parse_method().unwrap();
-        assert_eq!(method, ast::Method::Primitive { name: "hello".to_string(), parameters: vec![] });
+        let method = parser.parse_method().unwrap();
+        assert_eq!(method.without_spans(), ast::Method::Primitive {
+            name: name(&parser, "hello"),
+            parameters: vec![],
+            parameter_spans: vec![],
+            comments: vec![],
+            span: Span::default(),
+        });
     }
 
     #[test]
@@ -610,10 +1027,11 @@ mod tests {
         let mut parser = Parser::new(source, "test");
         let statements = parser.parse_block_body().unwrap();
         let statement = statements.first().unwrap();
-        assert_eq!(statement, &ast::Expression::Assignment {
-            variables: vec!["a".to_string()],
-            value: Box::new(ast::Expression::LiteralString("test".to_string())),
-        });
+        assert_eq!(statement.without_spans(), ExpressionKind::Assignment {
+            variables: vec![name(&parser, "a")],
+            variable_spans: vec![Span::default()],
+            value: Box::new(ExpressionKind::LiteralString("test".to_string()).into()),
+        }.into());
     }
 
     #[test]
@@ -622,10 +1040,19 @@ mod tests {
         let mut parser = Parser::new(source, "test");
         let statements = parser.parse_block_body().unwrap();
         let statement = statements.first().unwrap();
-        assert_eq!(statement, &ast::Expression::Assignment {
-            variables: vec!["a".to_string(), "b".to_string()],
-            value: Box::new(ast::Expression::LiteralString("test".to_string())),
-        });
+        assert_eq!(statement.without_spans(), ExpressionKind::Assignment {
+            variables: vec![name(&parser, "a"), name(&parser, "b")],
+            variable_spans: vec![Span::default(); 2],
+            value: Box::new(ExpressionKind::LiteralString("test".to_string()).into()),
+        }.into());
+
+        match statement.kind {
+            ExpressionKind::Assignment { ref variable_spans, .. } => assert_eq!(variable_spans, &[
+                Span::new(Location(1, 1, 0), Location(1, 2, 1)),
+                Span::new(Location(1, 6, 5), Location(1, 7, 6)),
+            ]),
+            ref kind => panic!("unexpected expression {:?}", kind),
+        }
     }
 
     #[test]
@@ -634,10 +1061,10 @@ mod tests {
         let mut parser = Parser::new(source, "test");
         let statements = parser.parse_block_body().unwrap();
         let statement = statements.first().unwrap();
-        assert_eq!(statement, &ast::Expression::UnaryMessage {
-            message: "println".to_string(),
-            receiver: Box::new(ast::Expression::LiteralString("test".to_string())),
-        });
+        assert_eq!(statement.without_spans(), ExpressionKind::UnaryMessage {
+            message: name(&parser, "println"),
+            receiver: Box::new(ExpressionKind::LiteralString("test".to_string()).into()),
+        }.into());
     }
 
     #[test]
@@ -645,20 +1072,22 @@ mod tests {
         let source = "[ :arg | arg print. ' ' print ]".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::Block {
-            parameters: vec!["arg".to_string()],
+        assert_eq!(expression.without_spans(), ExpressionKind::Block(ast::Block {
+            parameters: vec![name(&parser, "arg")],
+            parameter_spans: vec![Span::default()],
             locals: vec![],
             body: vec![
-                ast::Expression::UnaryMessage {
-                    message: "print".to_string(),
-                    receiver: Box::new(ast::Expression::Variable("arg".to_string())),
-                },
-                ast::Expression::UnaryMessage {
-                    message: "print".to_string(),
-                    receiver: Box::new(ast::Expression::LiteralString(" ".to_string())),
-                },
+                ExpressionKind::UnaryMessage {
+                    message: name(&parser, "print"),
+                    receiver: Box::new(ExpressionKind::Variable(name(&parser, "arg")).into()),
+                }.into(),
+                ExpressionKind::UnaryMessage {
+                    message: name(&parser, "print"),
+                    receiver: Box::new(ExpressionKind::LiteralString(" ".to_string()).into()),
+                }.into(),
             ],
-        });
+            span: Span::default(),
+        }).into());
     }
 
     #[test]
@@ -666,7 +1095,7 @@ mod tests {
         let source = "a".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::Variable("a".to_string()));
+        assert_eq!(expression.without_spans(), ExpressionKind::Variable(name(&parser, "a")).into());
     }
 
     #[test]
@@ -674,7 +1103,7 @@ mod tests {
         let source = "'test'".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralString("test".to_string()));
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralString("test".to_string()).into());
     }
 
     #[test]
@@ -682,7 +1111,7 @@ mod tests {
         let source = "nil".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralNil);
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralNil.into());
     }
 
     #[test]
@@ -690,11 +1119,11 @@ mod tests {
         let source = "true || false".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::BinaryMessage {
-            message: "||".to_string(),
-            left: Box::new(ast::Expression::LiteralBoolean(true)),
-            right: Box::new(ast::Expression::LiteralBoolean(false)),
-        });
+        assert_eq!(expression.without_spans(), ExpressionKind::BinaryMessage {
+            message: name(&parser, "||"),
+            left: Box::new(ExpressionKind::LiteralBoolean(true).into()),
+            right: Box::new(ExpressionKind::LiteralBoolean(false).into()),
+        }.into());
     }
 
     #[test]
@@ -702,11 +1131,11 @@ mod tests {
         let source = "#test #'test-case' #run:with:".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralSymbol("test".to_string()));
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralSymbol(name(&parser, "test")).into());
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralSymbol("test-case".to_string()));
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralSymbol(name(&parser, "test-case")).into());
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralSymbol("run:with:".to_string()));
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralSymbol(name(&parser, "run:with:")).into());
     }
 
     #[test]
@@ -714,7 +1143,7 @@ mod tests {
         let source = "1".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralInteger(1));
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralInteger(1).into());
     }
 
     #[test]
@@ -722,7 +1151,7 @@ mod tests {
         let source = "-1".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralInteger(-1));
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralInteger(-1).into());
     }
 
     #[test]
@@ -730,7 +1159,7 @@ mod tests {
         let source = "-3.14".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralDouble(-3.14));
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralDouble(-3.14).into());
     }
 
     #[test]
@@ -738,7 +1167,7 @@ mod tests {
         let source = "3.14".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralDouble(3.14));
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralDouble(3.14).into());
     }
 
     #[test]
@@ -746,10 +1175,10 @@ mod tests {
         let source = "1 println".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::UnaryMessage {
-            message: "println".to_string(),
-            receiver: Box::new(ast::Expression::LiteralInteger(1)),
-        });
+        assert_eq!(expression.without_spans(), ExpressionKind::UnaryMessage {
+            message: name(&parser, "println"),
+            receiver: Box::new(ExpressionKind::LiteralInteger(1).into()),
+        }.into());
     }
 
     #[test]
@@ -757,13 +1186,13 @@ mod tests {
         let source = "1 test println".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::UnaryMessage {
-            message: "println".to_string(),
-            receiver: Box::new(ast::Expression::UnaryMessage {
-                message: "test".to_string(),
-                receiver: Box::new(ast::Expression::LiteralInteger(1)),
-            }),
-        });
+        assert_eq!(expression.without_spans(), ExpressionKind::UnaryMessage {
+            message: name(&parser, "println"),
+            receiver: Box::new(ExpressionKind::UnaryMessage {
+                message: name(&parser, "test"),
+                receiver: Box::new(ExpressionKind::LiteralInteger(1).into()),
+            }.into()),
+        }.into());
     }
 
     #[test]
@@ -771,14 +1200,14 @@ mod tests {
         let source = "1 with: a and: b".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::KeywordMessage {
-            message: "with:and:".to_string(),
+        assert_eq!(expression.without_spans(), ExpressionKind::KeywordMessage {
+            message: name(&parser, "with:and:"),
             parameters: vec![
-                ast::Expression::Variable("a".to_string()),
-                ast::Expression::Variable("b".to_string()),
+                ExpressionKind::Variable(name(&parser, "a")).into(),
+                ExpressionKind::Variable(name(&parser, "b")).into(),
             ],
-            receiver: Box::new(ast::Expression::LiteralInteger(1)),
-        });
+            receiver: Box::new(ExpressionKind::LiteralInteger(1).into()),
+        }.into());
     }
 
     #[test]
@@ -787,21 +1216,21 @@ mod tests {
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
         println!("expression: {:#?}", expression);
-        assert_eq!(expression, ast::Expression::KeywordMessage {
-            message: "with:and:".to_string(),
+        assert_eq!(expression.without_spans(), ExpressionKind::KeywordMessage {
+            message: name(&parser, "with:and:"),
             parameters: vec![
-                ast::Expression::UnaryMessage {
-                    message: "length".to_string(),
-                    receiver: Box::new(ast::Expression::Variable("a".to_string())),
-                },
-                ast::Expression::BinaryMessage {
-                    message: "+".to_string(),
-                    left: Box::new(ast::Expression::LiteralInteger(1)),
-                    right: Box::new(ast::Expression::LiteralInteger(2)),
-                },
+                ExpressionKind::UnaryMessage {
+                    message: name(&parser, "length"),
+                    receiver: Box::new(ExpressionKind::Variable(name(&parser, "a")).into()),
+                }.into(),
+                ExpressionKind::BinaryMessage {
+                    message: name(&parser, "+"),
+                    left: Box::new(ExpressionKind::LiteralInteger(1).into()),
+                    right: Box::new(ExpressionKind::LiteralInteger(2).into()),
+                }.into(),
             ],
-            receiver: Box::new(ast::Expression::LiteralInteger(1)),
-        });
+            receiver: Box::new(ExpressionKind::LiteralInteger(1).into()),
+        }.into());
     }
 
     #[test]
@@ -809,11 +1238,11 @@ mod tests {
         let source = "1 + 2".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::BinaryMessage {
-            message: "+".to_string(),
-            left: Box::new(ast::Expression::LiteralInteger(1)),
-            right: Box::new(ast::Expression::LiteralInteger(2)),
-        });
+        assert_eq!(expression.without_spans(), ExpressionKind::BinaryMessage {
+            message: name(&parser, "+"),
+            left: Box::new(ExpressionKind::LiteralInteger(1).into()),
+            right: Box::new(ExpressionKind::LiteralInteger(2).into()),
+        }.into());
     }
 
     #[test]
@@ -821,11 +1250,11 @@ mod tests {
         let source = "1 <= 2".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::BinaryMessage {
-            message: "<=".to_string(),
-            left: Box::new(ast::Expression::LiteralInteger(1)),
-            right: Box::new(ast::Expression::LiteralInteger(2)),
-        });
+        assert_eq!(expression.without_spans(), ExpressionKind::BinaryMessage {
+            message: name(&parser, "<="),
+            left: Box::new(ExpressionKind::LiteralInteger(1).into()),
+            right: Box::new(ExpressionKind::LiteralInteger(2).into()),
+        }.into());
     }
 
     #[test]
@@ -834,15 +1263,15 @@ mod tests {
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
         println!("expression: {:#?}", expression);
-        assert_eq!(expression, ast::Expression::BinaryMessage {
-            message: "+".to_string(),
-            left: Box::new(ast::Expression::LiteralInteger(1)),
-            right: Box::new(ast::Expression::BinaryMessage {
-                message: "-".to_string(),
-                left: Box::new(ast::Expression::LiteralInteger(2)),
-                right: Box::new(ast::Expression::LiteralInteger(1)),
-            }),
-        });
+        assert_eq!(expression.without_spans(), ExpressionKind::BinaryMessage {
+            message: name(&parser, "+"),
+            left: Box::new(ExpressionKind::LiteralInteger(1).into()),
+            right: Box::new(ExpressionKind::BinaryMessage {
+                message: name(&parser, "-"),
+                left: Box::new(ExpressionKind::LiteralInteger(2).into()),
+                right: Box::new(ExpressionKind::LiteralInteger(1).into()),
+            }.into()),
+        }.into());
     }
 
     #[test]
@@ -850,14 +1279,14 @@ mod tests {
         let source = "1 test + 2".as_bytes();
         let mut parser = Parser::new(source, "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::BinaryMessage {
-            message: "+".to_string(),
-            left: Box::new(ast::Expression::UnaryMessage {
-                receiver: Box::new(ast::Expression::LiteralInteger(1)),
-                message: "test".to_string(),
-            }),
-            right: Box::new(ast::Expression::LiteralInteger(2)),
-        });
+        assert_eq!(expression.without_spans(), ExpressionKind::BinaryMessage {
+            message: name(&parser, "+"),
+            left: Box::new(ExpressionKind::UnaryMessage {
+                receiver: Box::new(ExpressionKind::LiteralInteger(1).into()),
+                message: name(&parser, "test"),
+            }.into()),
+            right: Box::new(ExpressionKind::LiteralInteger(2).into()),
+        }.into());
     }
 
     #[test]
@@ -865,8 +1294,26 @@ mod tests {
         let source = "Hello = Test ()".as_bytes();
         let mut parser = Parser::new(source, "test");
         let class = parser.parse_class().unwrap();
-        assert_eq!(class.superclass, "Test");
-        assert_eq!(class.name, "Hello");
+        assert_eq!(class.superclass, ast::Superclass::Named(name(&parser, "Test")));
+        assert_eq!(class.name, name(&parser, "Hello"));
+    }
+
+    #[test]
+    fn nil_and_implicit_superclasses() {
+        let parse = |source: &str| Parser::new(source.as_bytes(), "test").parse_class().unwrap().superclass;
+        assert_eq!(parse("Object = nil ()"), ast::Superclass::None);
+        assert_eq!(parse("Hello = ()"), ast::Superclass::Implicit);
+
+        let mut parser = Parser::new("Hello = Object ()".as_bytes(), "test");
+        let class = parser.parse_class().unwrap();
+        let mut interner = parser.into_interner();
+        let object = interner.get("Object").unwrap();
+        assert_eq!(class.superclass, ast::Superclass::Named(object));
+
+        let class_name = interner.intern("Class");
+        assert_eq!(ast::Superclass::None.name(&mut interner), None);
+        assert_eq!(ast::Superclass::Implicit.name(&mut interner), Some(object));
+        assert_eq!(ast::Superclass::Named(class_name).name(&mut interner), Some(class_name));
     }
 
     #[test]
@@ -877,17 +1324,23 @@ mod tests {
         )
         ".as_bytes();
         let mut parser = Parser::new(source, "test");
-        let (_, method) = parser.parse_method().unwrap();
-        assert_eq!(method, ast::Method::Native {
-            name: "test".to_string(),
-            parameters: vec![],
-            locals: vec!["a".to_string(), "b".to_string()],
-            body: vec![
-                ast::Expression::UnaryMessage {
-                    receiver: Box::new(ast::Expression::Variable("a".to_string())),
-                    message: "println".to_string(),
-                },
-            ],
+        let method = parser.parse_method().unwrap();
+        assert_eq!(method.without_spans(), ast::Method::Native {
+            name: name(&parser, "test"),
+            body: ast::Block {
+                parameters: vec![],
+                parameter_spans: vec![],
+                locals: vec![name(&parser, "a"), name(&parser, "b")],
+                body: vec![
+                    ExpressionKind::UnaryMessage {
+                        receiver: Box::new(ExpressionKind::Variable(name(&parser, "a")).into()),
+                        message: name(&parser, "println"),
+                    }.into(),
+                ],
+                span: Span::default(),
+            },
+            comments: vec![],
+            span: Span::default(),
         });
     }
 
@@ -900,21 +1353,27 @@ mod tests {
         )
         ".as_bytes();
         let mut parser = Parser::new(source, "test");
-        let (_, method) = parser.parse_method().unwrap();
-        assert_eq!(method, ast::Method::Native {
-            name: "test".to_string(),
-            parameters: vec![],
-            locals: vec!["a".to_string(), "b".to_string()],
-            body: vec![
-                ast::Expression::UnaryMessage {
-                    receiver: Box::new(ast::Expression::Variable("a".to_string())),
-                    message: "println".to_string(),
-                },
-                ast::Expression::UnaryMessage {
-                    receiver: Box::new(ast::Expression::Variable("b".to_string())),
-                    message: "println".to_string(),
-                },
-            ],
+        let method = parser.parse_method().unwrap();
+        assert_eq!(method.without_spans(), ast::Method::Native {
+            name: name(&parser, "test"),
+            body: ast::Block {
+                parameters: vec![],
+                parameter_spans: vec![],
+                locals: vec![name(&parser, "a"), name(&parser, "b")],
+                body: vec![
+                    ExpressionKind::UnaryMessage {
+                        receiver: Box::new(ExpressionKind::Variable(name(&parser, "a")).into()),
+                        message: name(&parser, "println"),
+                    }.into(),
+                    ExpressionKind::UnaryMessage {
+                        receiver: Box::new(ExpressionKind::Variable(name(&parser, "b")).into()),
+                        message: name(&parser, "println"),
+                    }.into(),
+                ],
+                span: Span::default(),
+            },
+            comments: vec![],
+            span: Span::default(),
         });
     }
 
@@ -926,17 +1385,23 @@ mod tests {
         )
         ".as_bytes();
         let mut parser = Parser::new(source, "test");
-        let (_, method) = parser.parse_method().unwrap();
-        assert_eq!(method, ast::Method::Native {
-            name: "test:with:".to_string(),
-            parameters: vec!["a".to_string(), "b".to_string()],
-            locals: vec![],
-            body: vec![
-                ast::Expression::UnaryMessage {
-                    receiver: Box::new(ast::Expression::Variable("a".to_string())),
-                    message: "println".to_string(),
-                },
-            ],
+        let method = parser.parse_method().unwrap();
+        assert_eq!(method.without_spans(), ast::Method::Native {
+            name: name(&parser, "test:with:"),
+            body: ast::Block {
+                parameters: vec![name(&parser, "a"), name(&parser, "b")],
+                parameter_spans: vec![Span::default(); 2],
+                locals: vec![],
+                body: vec![
+                    ExpressionKind::UnaryMessage {
+                        receiver: Box::new(ExpressionKind::Variable(name(&parser, "a")).into()),
+                        message: name(&parser, "println"),
+                    }.into(),
+                ],
+                span: Span::default(),
+            },
+            comments: vec![],
+            span: Span::default(),
         });
     }
 
@@ -948,20 +1413,711 @@ mod tests {
         )
         ".as_bytes();
         let mut parser = Parser::new(source, "test");
-        let (_, method) = parser.parse_method().unwrap();
-        assert_eq!(method, ast::Method::Native {
-            name: "test".to_string(),
-            parameters: vec![],
-            locals: vec![],
-            body: vec![
-                ast::Expression::Return(Box::new(
-                    ast::Expression::BinaryMessage {
-                        message: "+".to_string(),
-                        left: Box::new(ast::Expression::LiteralInteger(1)),
-                        right: Box::new(ast::Expression::LiteralInteger(1)),
-                    },
-                )),
-            ],
+        let method = parser.parse_method().unwrap();
+        assert_eq!(method.without_spans(), ast::Method::Native {
+            name: name(&parser, "test"),
+            body: ast::Block {
+                parameters: vec![],
+                parameter_spans: vec![],
+                locals: vec![],
+                body: vec![
+                    ExpressionKind::Return(Box::new(
+                        ExpressionKind::BinaryMessage {
+                            message: name(&parser, "+"),
+                            left: Box::new(ExpressionKind::LiteralInteger(1).into()),
+                            right: Box::new(ExpressionKind::LiteralInteger(1).into()),
+                        }.into(),
+                    )).into(),
+                ],
+                span: Span::default(),
+            },
+            comments: vec![],
+            span: Span::default(),
         });
     }
+
+    #[test]
+    fn expression_span() {
+        let source = "1 + 2".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(expression.span, Span::new(Location(1, 1, 0), Location(1, 6, 5)));
+    }
+
+    #[test]
+    fn nested_term_span_includes_parentheses() {
+        let source = "(1 + 2) abs".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(expression.span, Span::new(Location(1, 1, 0), Location(1, 12, 11)));
+        match expression.kind {
+            ExpressionKind::UnaryMessage { receiver, .. } => {
+                assert_eq!(receiver.span, Span::new(Location(1, 1, 0), Location(1, 8, 7)));
+            }
+            kind => panic!("unexpected expression: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn negative_number_span() {
+        let source = "-42".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(expression.span, Span::new(Location(1, 1, 0), Location(1, 4, 3)));
+    }
+
+    #[test]
+    fn method_span() {
+        let source = "
+        test = (
+            ^ 1
+        )
+        ".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let method = parser.parse_method().unwrap();
+        assert_eq!(method.span(), Span::new(Location(2, 9, 9), Location(4, 10, 43)));
+    }
+
+    #[test]
+    fn class_span() {
+        let source = "Hello = (\n    run = primitive\n)\n".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let class = parser.parse_class().unwrap();
+        assert_eq!(class.span, Span::new(Location(1, 1, 0), Location(3, 2, 31)));
+        assert_eq!(class.instance_methods[&name(&parser, "run")].span(), Span::new(Location(2, 5, 14), Location(2, 20, 29)));
+    }
+
+    #[test]
+    fn recovery_replaces_bad_statement_with_error_node() {
+        let source = "
+        Hello = (
+            run = (
+                1 println.
+                a := := 3.
+                2 println
+            )
+            other = ( ^ 3 )
+        )
+        ".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let (class, diagnostics) = parser.parse_class_with_recovery();
+        let class = class.unwrap();
+        assert_eq!(diagnostics, vec![Error::UnexpectedTokenError {
+            text: ":=".to_string(),
+            location: Location(5, 22, 87),
+        }]);
+        assert_eq!(class.instance_methods.len(), 2);
+        match class.instance_methods[&name(&parser, "run")] {
+            ast::Method::Native { ref body, .. } => {
+                let kinds: Vec<_> = body.body.iter().map(|statement| statement.without_spans().kind).collect();
+                assert_eq!(kinds[1], ExpressionKind::Error);
+                assert_eq!(kinds.len(), 3);
+            }
+            ref method => panic!("unexpected method: {:?}", method),
+        }
+    }
+
+    #[test]
+    fn recovery_reports_multiple_diagnostics() {
+        let source = "
+        Hello = (
+            first = ( 1 + ] )
+            second: = ( 2 )
+            third = ( #. 3 )
+            fourth = ( ^ 4 )
+        )
+        ".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let (class, diagnostics) = parser.parse_class_with_recovery();
+        let class = class.unwrap();
+        let lines: Vec<_> = diagnostics.iter().map(|diagnostic| diagnostic.position().unwrap().0).collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+        let names: Vec<_> = class.instance_methods.names().map(|name| parser.interner().resolve(name)).collect();
+        assert_eq!(names, vec!["first", "third", "fourth"]);
+    }
+
+    #[test]
+    fn recovery_resynchronizes_on_method_definition() {
+        let source = "
+        Hello = (
+            broken = ( 1 foo: :
+            working = ( ^ 2 )
+        )
+        ".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let (class, diagnostics) = parser.parse_class_with_recovery();
+        let class = class.unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert!(class.instance_methods.contains_key(&name(&parser, "working")));
+    }
+
+    #[test]
+    fn recovery_returns_partial_class_at_end_of_input() {
+        let source = "Hello = ( run = ( 1 println".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let (class, diagnostics) = parser.parse_class_with_recovery();
+        assert_eq!(class.unwrap().name, name(&parser, "Hello"));
+        assert_eq!(diagnostics, vec![Error::ParseError {
+            description: "Unexpected end of input".to_string(),
+            filename: "test".to_string(),
+            line: 1,
+            position: 28,
+        }]);
+    }
+
+    #[test]
+    fn recovery_without_errors() {
+        let source = "Hello = ( run = ( ^ 1 ) )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let (class, diagnostics) = parser.parse_class_with_recovery();
+        assert!(class.is_some());
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn unexpected_token_without_recovery() {
+        let source = "Hello = ( run = ( a := ) )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        assert_eq!(parser.parse_class(), Err(Error::UnexpectedTokenError {
+            text: ")".to_string(),
+            location: Location(1, 24, 23),
+        }));
+    }
+
+    #[test]
+    fn unknown_character_error() {
+        let source = "a := ?".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        assert_eq!(parser.parse_expression(), Err(Error::LexicalError(lexer::Error::UnknownCharacterError {
+            text: "?".to_string(),
+            location: Location(1, 6, 5),
+        })));
+    }
+
+    #[test]
+    fn unknown_character_is_not_skipped_by_lookahead() {
+        let source = "Hello = ( run = ( 1 println ? ) )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        assert_eq!(parser.parse_class(), Err(Error::LexicalError(lexer::Error::UnknownCharacterError {
+            text: "?".to_string(),
+            location: Location(1, 29, 28),
+        })));
+    }
+
+    #[test]
+    fn unknown_character_recovery() {
+        let source = "Hello = ( run = ( 1 ? println. 2 println ) )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let (class, diagnostics) = parser.parse_class_with_recovery();
+        assert_eq!(diagnostics.len(), 1);
+        match class.unwrap().instance_methods[&name(&parser, "run")] {
+            ast::Method::Native { ref body, .. } => assert_eq!(body.body.len(), 2),
+            ref method => panic!("unexpected method: {:?}", method),
+        }
+    }
+
+    #[test]
+    fn unterminated_string_error() {
+        let source = "Hello = (\n    run = ( 'Hello println )\n)\n".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        assert_eq!(parser.parse_class(), Err(Error::LexicalError(lexer::Error::UnterminatedStringError {
+            location: Location(2, 13, 22),
+        })));
+    }
+
+    #[test]
+    fn rejects_input_after_class() {
+        let mut parser = Parser::new("Test = ( run = ( ) ) )".as_bytes(), "test");
+        assert_eq!(parser.parse_class(), Err(Error::UnexpectedTokenError {
+            text: ")".to_string(),
+            location: Location(1, 22, 21),
+        }));
+
+        let mut parser = Parser::new("Test = ( ) 'open".as_bytes(), "test");
+        assert_eq!(parser.parse_class(), Err(Error::LexicalError(lexer::Error::UnterminatedStringError {
+            location: Location(1, 12, 11),
+        })));
+
+        let mut parser = Parser::new("Test = ( ) \"trailing\"\n".as_bytes(), "test");
+        assert!(parser.parse_class().is_ok());
+    }
+
+    #[test]
+    fn unterminated_comment_recovery() {
+        let source = "Hello = (\n    run = ( ^ 1 )\n    \"unfinished\n".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let (class, diagnostics) = parser.parse_class_with_recovery();
+        assert!(class.unwrap().instance_methods.contains_key(&name(&parser, "run")));
+        assert_eq!(diagnostics[0], Error::LexicalError(lexer::Error::UnterminatedCommentError {
+            location: Location(3, 5, 32),
+        }));
+    }
+
+    #[test]
+    fn string_escapes_in_literals() {
+        let source = r"'it''s\n' , #'a\tb'".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(expression.without_spans(), ExpressionKind::BinaryMessage {
+            message: name(&parser, ","),
+            left: Box::new(ExpressionKind::LiteralString("it's\n".to_string()).into()),
+            right: Box::new(ExpressionKind::LiteralSymbol(name(&parser, "a\tb")).into()),
+        }.into());
+    }
+
+    #[test]
+    fn unexpected_token_in_symbol() {
+        let source = "#)".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        assert_eq!(parser.parse_expression(), Err(Error::UnexpectedTokenError {
+            text: ")".to_string(),
+            location: Location(1, 2, 1),
+        }));
+    }
+
+    #[test]
+    fn literal_big_integer_expression() {
+        let source = "100000000000000000000".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression().unwrap();
+        let value = "100000000000000000000".parse().unwrap();
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralBigInteger(value).into());
+    }
+
+    #[test]
+    fn literal_negative_big_integer_expression() {
+        let source = "-100000000000000000000".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression().unwrap();
+        let value = "-100000000000000000000".parse().unwrap();
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralBigInteger(value).into());
+    }
+
+    #[test]
+    fn integer_boundaries() {
+        let source = "9223372036854775807 9223372036854775808 (-9223372036854775809)".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression_primary().unwrap();
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralInteger(i64::MAX).into());
+        let expression = parser.parse_expression_primary().unwrap();
+        let value = BigInt::from(i64::MAX) + 1;
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralBigInteger(value).into());
+        let expression = parser.parse_expression_primary().unwrap();
+        let value = BigInt::from(i64::MIN) - 1;
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralBigInteger(value).into());
+    }
+
+    #[test]
+    fn smallest_negative_integer() {
+        let source = "-9223372036854775808".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralInteger(i64::MIN).into());
+    }
+
+    #[test]
+    fn radix_and_exponent_literals() {
+        let source = "-16r1F 1.5e-3 1e2".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression_primary().unwrap();
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralInteger(-31).into());
+        let expression = parser.parse_expression_primary().unwrap();
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralDouble(0.0015).into());
+        let expression = parser.parse_expression_primary().unwrap();
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralInteger(100).into());
+    }
+
+    #[test]
+    fn literal_array_expression() {
+        let source = "#(1 -2 #foo 'bar' $a #(nested) (true nil) at:put: + -)".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let expression = parser.parse_expression().unwrap();
+        let symbol = |text: &str| ast::Expression::from(ExpressionKind::LiteralSymbol(name(&parser, text)));
+        assert_eq!(expression.without_spans(), ExpressionKind::LiteralArray(vec![
+            ExpressionKind::LiteralInteger(1).into(),
+            ExpressionKind::LiteralInteger(-2).into(),
+            symbol("foo"),
+            ExpressionKind::LiteralString("bar".to_string()).into(),
+            ExpressionKind::LiteralCharacter('a').into(),
+            ExpressionKind::LiteralArray(vec![symbol("nested")]).into(),
+            ExpressionKind::LiteralArray(vec![
+                ExpressionKind::LiteralBoolean(true).into(),
+                ExpressionKind::LiteralNil.into(),
+            ]).into(),
+            symbol("at:put:"),
+            symbol("+"),
+            symbol("-"),
+        ]).into());
+        assert_eq!(expression.span, Span::new(Location(1, 1, 0), Location(1, 55, 54)));
+    }
+
+    #[test]
+    fn unterminated_literal_array() {
+        let source = "#(1 2".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        assert!(parser.parse_expression().is_err());
+    }
+
+    #[test]
+    fn methods_keep_declaration_order() {
+        let source = "Hello = ( zeta = ( ^ 1 ) alpha = ( ^ 2 ) + other = ( ^ 3 ) ---- | b a | mid = ( ^ 4 ) first = ( ^ 5 ) )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let class = parser.parse_class().unwrap();
+        let names: Vec<_> = class.instance_methods.names().map(|name| parser.interner().resolve(name)).collect();
+        assert_eq!(names, vec!["zeta", "alpha", "+"]);
+        let names: Vec<_> = class.class_methods.names().map(|name| parser.interner().resolve(name)).collect();
+        assert_eq!(names, vec!["mid", "first"]);
+        assert_eq!(class.class_variables, vec![name(&parser, "b"), name(&parser, "a")]);
+        assert_eq!(class.instance_methods[&name(&parser, "alpha")].name(), name(&parser, "alpha"));
+    }
+
+    #[test]
+    fn duplicate_method_error() {
+        let source = "Hello = ( run = ( ^ 1 ) run = ( ^ 2 ) )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        assert_eq!(parser.parse_class(), Err(Error::DuplicateMethodError {
+            name: name(&parser, "run"),
+            location: Location(1, 25, 24),
+        }));
+    }
+
+    #[test]
+    fn duplicate_method_recovery_keeps_first_definition() {
+        let source = "Hello = ( run = ( ^ 1 ) run = ( ^ 2 ) other = ( ^ 3 ) )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let (class, diagnostics) = parser.parse_class_with_recovery();
+        assert_eq!(diagnostics, vec![Error::DuplicateMethodError {
+            name: name(&parser, "run"),
+            location: Location(1, 25, 24),
+        }]);
+        let class = class.unwrap();
+        assert_eq!(class.instance_methods.len(), 2);
+        match class.instance_methods[&name(&parser, "run")] {
+            ast::Method::Native { ref body, .. } => assert_eq!(body.body[0].without_spans(), ExpressionKind::Return(
+                Box::new(ExpressionKind::LiteralInteger(1).into())
+            ).into()),
+            ref method => panic!("unexpected method: {:?}", method),
+        }
+    }
+
+    #[test]
+    fn duplicate_and_reserved_variables() {
+        let source = "Hello = ( | a b a self | ---- | c c | )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let (class, diagnostics) = parser.parse_class_with_recovery();
+        assert_eq!(diagnostics, vec![
+            Error::DuplicateVariableError { name: name(&parser, "a"), location: Location(1, 17, 16) },
+            Error::ReservedNameError { name: name(&parser, "self"), location: Location(1, 19, 18) },
+            Error::DuplicateVariableError { name: name(&parser, "c"), location: Location(1, 35, 34) },
+        ]);
+        let class = class.unwrap();
+        assert_eq!(class.instance_variables, vec![name(&parser, "a"), name(&parser, "b"), name(&parser, "self")]);
+        assert_eq!(class.class_variables, vec![name(&parser, "c")]);
+    }
+
+    #[test]
+    fn duplicate_variable_without_recovery() {
+        let source = "Hello = ( | a a | )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        assert_eq!(parser.parse_class(), Err(Error::DuplicateVariableError {
+            name: name(&parser, "a"),
+            location: Location(1, 15, 14),
+        }));
+    }
+
+    #[test]
+    fn trivia_attaches_leading_comments() {
+        let source = "
+        \"A class\" \"in two parts\"
+        Hello = (
+            | a |
+            \"Runs\"
+            run = ( \"ignored\" ^ a )
+            other = primitive
+            ----
+            \"Creates\"
+            new = primitive
+        )
+        ".as_bytes();
+        let mut parser = Parser::new(source, "test").with_trivia();
+        let class = parser.parse_class().unwrap();
+        assert_eq!(class.comments, vec!["A class".to_string(), "in two parts".to_string()]);
+        assert_eq!(class.instance_methods[&name(&parser, "run")].comments(), &["Runs".to_string()]);
+        assert!(class.instance_methods[&name(&parser, "other")].comments().is_empty());
+        assert_eq!(class.class_methods[&name(&parser, "new")].comments(), &["Creates".to_string()]);
+        assert!(parser.leading_comments.is_empty());
+    }
+
+    #[test]
+    fn body_comments_are_discarded() {
+        let source = "Hello = ( run = ( \"a\" ^ 1 \"b\" ) stop = ( \"c\" ^ 2 ) )".as_bytes();
+        let mut parser = Parser::new(source, "test").with_trivia();
+        let class = parser.parse_class().unwrap();
+        assert!(class.instance_methods.iter().all(|method| method.comments().is_empty()));
+        assert!(parser.leading_comments.is_empty());
+    }
+
+    #[test]
+    fn comments_are_dropped_without_trivia() {
+        let source = "\"A class\" Hello = ( \"Runs\" run = ( ^ 1 ) )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        let class = parser.parse_class().unwrap();
+        assert!(class.comments.is_empty());
+        assert!(class.instance_methods[&name(&parser, "run")].comments().is_empty());
+    }
+
+    #[test]
+    fn trivia_does_not_change_spans() {
+        let source = "Hello = ( \"Runs\" run = ( ^ 1 ) )";
+        let mut parser = Parser::new(source.as_bytes(), "test");
+        let plain = parser.parse_class().unwrap();
+        let run = name(&parser, "run");
+        let trivia = Parser::new(source.as_bytes(), "test").with_trivia().parse_class().unwrap();
+        assert_eq!(plain.span, trivia.span);
+        assert_eq!(plain.instance_methods[&run].span(), trivia.instance_methods[&run].span());
+    }
+
+    #[test]
+    fn cascade_expression() {
+        let source = "Transcript show: 'a' , b; show: 'b'; cr; + 1".as_bytes();
+        let options = ParserOptions { cascades: true, ..Default::default() };
+        let mut parser = Parser::new(source, "test").with_options(options);
+        let expression = parser.parse_expression().unwrap();
+        let string = |value: &str| ast::Expression::from(ExpressionKind::LiteralString(value.to_string()));
+        assert_eq!(expression.without_spans(), ExpressionKind::Cascade {
+            receiver: Box::new(ExpressionKind::Variable(name(&parser, "Transcript")).into()),
+            messages: vec![
+                ast::Message {
+                    selector: name(&parser, "show:"),
+                    arguments: vec![ExpressionKind::BinaryMessage {
+                        message: name(&parser, ","),
+                        left: Box::new(string("a")),
+                        right: Box::new(ExpressionKind::Variable(name(&parser, "b")).into()),
+                    }.into()],
+                },
+                ast::Message { selector: name(&parser, "show:"), arguments: vec![string("b")] },
+                ast::Message { selector: name(&parser, "cr"), arguments: vec![] },
+                ast::Message { selector: name(&parser, "+"), arguments: vec![ExpressionKind::LiteralInteger(1).into()] },
+            ],
+        }.into());
+        assert_eq!(expression.span, Span::new(Location(1, 1, 0), Location(1, 45, 44)));
+    }
+
+    #[test]
+    fn cascade_receiver_is_last_message_receiver() {
+        let source = "x := a foo bar; baz".as_bytes();
+        let mut parser = Parser::new(source, "test").with_options(ParserOptions { cascades: true, ..Default::default() });
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(expression.without_spans(), ExpressionKind::Assignment {
+            variables: vec![name(&parser, "x")],
+            variable_spans: vec![Span::default()],
+            value: Box::new(ExpressionKind::Cascade {
+                receiver: Box::new(ExpressionKind::UnaryMessage {
+                    message: name(&parser, "foo"),
+                    receiver: Box::new(ExpressionKind::Variable(name(&parser, "a")).into()),
+                }.into()),
+                messages: vec![
+                    ast::Message { selector: name(&parser, "bar"), arguments: vec![] },
+                    ast::Message { selector: name(&parser, "baz"), arguments: vec![] },
+                ],
+            }.into()),
+        }.into());
+    }
+
+    #[test]
+    fn cascade_requires_message_send() {
+        let source = "a; foo".as_bytes();
+        let mut parser = Parser::new(source, "test").with_options(ParserOptions { cascades: true, ..Default::default() });
+        assert_eq!(parser.parse_expression(), Err(Error::UnexpectedTokenError {
+            text: ";".to_string(),
+            location: Location(1, 2, 1),
+        }));
+    }
+
+    #[test]
+    fn cascades_rejected_in_strict_mode() {
+        let source = "Test = ( run = ( a foo; bar ) )".as_bytes();
+        let mut parser = Parser::new(source, "test");
+        assert_eq!(parser.parse_class(), Err(Error::ParseError {
+            description: "Expected [EndTerm], found Semicolon".to_string(),
+            filename: "test".to_string(),
+            line: 1,
+            position: 23,
+        }));
+    }
+
+    fn parse_dialect(dialect: Dialect, source: &str) -> (ast::Class, Parser<&[u8], &'static str>) {
+        let mut parser = Parser::new(source.as_bytes(), "test").with_options(dialect.into());
+        (parser.parse_class().unwrap(), parser)
+    }
+
+    fn first_statement(class: &ast::Class) -> ast::Expression {
+        match class.instance_methods.iter().next() {
+            Some(ast::Method::Native { body, .. }) => body.body[0].without_spans(),
+            method => panic!("unexpected method {:?}", method),
+        }
+    }
+
+    #[test]
+    fn som_java_dialect() {
+        let (class, parser) = parse_dialect(Dialect::SomJava, r"Test = ( run = ( ^ #(#at:put: 'a\tb') ) ---- )");
+        assert_eq!(class.superclass, ast::Superclass::Implicit);
+        assert_eq!(first_statement(&class), ExpressionKind::Return(Box::new(ExpressionKind::LiteralArray(vec![
+            ExpressionKind::LiteralSymbol(name(&parser, "at:put:")).into(),
+            ExpressionKind::LiteralString("a\tb".to_string()).into(),
+        ]).into())).into());
+    }
+
+    #[test]
+    fn som_cpp_dialect_keeps_backslashes() {
+        let (class, _) = parse_dialect(Dialect::SomCpp, r"Test = ( run = ( ^ 'a\tb\' ) )");
+        assert_eq!(first_statement(&class), ExpressionKind::Return(Box::new(
+            ExpressionKind::LiteralString("a\\tb\\".to_string()).into(),
+        )).into());
+    }
+
+    #[test]
+    fn truffle_som_dialect() {
+        assert_eq!(Dialect::TruffleSom.options(), Dialect::SomJava.options());
+        let (class, parser) = parse_dialect(Dialect::TruffleSom, "Test = Base ( run = ( ^ #value:value: ) )");
+        assert_eq!(class.superclass, ast::Superclass::Named(name(&parser, "Base")));
+        assert_eq!(first_statement(&class), ExpressionKind::Return(Box::new(
+            ExpressionKind::LiteralSymbol(name(&parser, "value:value:")).into(),
+        )).into());
+
+        let source = "Test = ( run = ( ^ d at:key ) )".as_bytes();
+        let mut parser = Parser::new(source, "test").with_options(Dialect::TruffleSom.into());
+        assert_eq!(parser.parse_class(), Err(Error::ParseError {
+            description: "Expected [EndTerm], found KeywordSequence".to_string(),
+            filename: "test".to_string(),
+            line: 1,
+            position: 22,
+        }));
+    }
+
+    #[test]
+    fn som_ns_lite_dialect() {
+        let source = "Test = ( run = ( d at:key put:#at:put:; yourself ) ---------- | count | )";
+        let (class, parser) = parse_dialect(Dialect::SomNsLite, source);
+        assert_eq!(class.superclass, ast::Superclass::None);
+        assert_eq!(class.class_variables, vec![name(&parser, "count")]);
+        assert_eq!(first_statement(&class), ExpressionKind::Cascade {
+            receiver: Box::new(ExpressionKind::Variable(name(&parser, "d")).into()),
+            messages: vec![
+                ast::Message {
+                    selector: name(&parser, "at:put:"),
+                    arguments: vec![
+                        ExpressionKind::Variable(name(&parser, "key")).into(),
+                        ExpressionKind::LiteralSymbol(name(&parser, "at:put:")).into(),
+                    ],
+                },
+                ast::Message { selector: name(&parser, "yourself"), arguments: vec![] },
+            ],
+        }.into());
+    }
+
+    #[test]
+    fn som_ns_lite_dialect_rejects_short_separator() {
+        let source = "Test = ( ---- )".as_bytes();
+        let mut parser = Parser::new(source, "test").with_options(Dialect::SomNsLite.into());
+        assert!(parser.parse_class().is_err());
+    }
+
+    #[test]
+    fn standalone_expression() {
+        let mut parser = Parser::new("1 + 2 printString".as_bytes(), "test");
+        assert_eq!(parser.parse_standalone_expression().unwrap().without_spans(), ExpressionKind::BinaryMessage {
+            message: name(&parser, "+"),
+            left: Box::new(ExpressionKind::LiteralInteger(1).into()),
+            right: Box::new(ExpressionKind::UnaryMessage {
+                message: name(&parser, "printString"),
+                receiver: Box::new(ExpressionKind::LiteralInteger(2).into()),
+            }.into()),
+        }.into());
+    }
+
+    #[test]
+    fn standalone_expression_rejects_trailing_input() {
+        let mut parser = Parser::new("1 + 2. 3".as_bytes(), "test");
+        assert_eq!(parser.parse_standalone_expression(), Err(Error::UnexpectedTokenError {
+            text: ".".to_string(),
+            location: Location(1, 6, 5),
+        }));
+    }
+
+    #[test]
+    fn standalone_expression_reports_end_of_input() {
+        let mut parser = Parser::new("1 +".as_bytes(), "test");
+        assert_eq!(parser.parse_standalone_expression(), Err(Error::ParseError {
+            description: "Unexpected end of input".to_string(),
+            filename: "test".to_string(),
+            line: 1,
+            position: 4,
+        }));
+    }
+
+    #[test]
+    fn statements_with_locals() {
+        let mut parser = Parser::new("| a | a := 3. ^ a".as_bytes(), "test");
+        let block = parser.parse_statements().unwrap();
+        assert_eq!(block.locals, vec![name(&parser, "a")]);
+        assert_eq!(block.body.len(), 2);
+        assert_eq!(block.span, Span::new(Location(1, 1, 0), Location(1, 18, 17)));
+
+        let mut parser = Parser::new("a foo ]".as_bytes(), "test");
+        assert_eq!(parser.parse_statements(), Err(Error::UnexpectedTokenError {
+            text: "]".to_string(),
+            location: Location(1, 7, 6),
+        }));
+    }
+
+    #[test]
+    fn standalone_method() {
+        let mut parser = Parser::new("at: i = ( ^ i )".as_bytes(), "test");
+        let method = parser.parse_standalone_method().unwrap();
+        assert_eq!(method.name(), name(&parser, "at:"));
+        assert_eq!(method.parameters(), &[name(&parser, "i")]);
+
+        let mut parser = Parser::new("run = ( ) extra".as_bytes(), "test");
+        assert_eq!(parser.parse_standalone_method(), Err(Error::UnexpectedTokenError {
+            text: "extra".to_string(),
+            location: Location(1, 11, 10),
+        }));
+    }
+
+    #[test]
+    fn records_parameter_spans() {
+        let mut parser = Parser::new("+ other = ( ^ other )".as_bytes(), "test");
+        let method = parser.parse_standalone_method().unwrap();
+        assert_eq!(method.parameter_spans(), &[Span::new(Location(1, 3, 2), Location(1, 8, 7))]);
+
+        let mut parser = Parser::new("at: i put: v = primitive".as_bytes(), "test");
+        let method = parser.parse_standalone_method().unwrap();
+        assert_eq!(method.parameter_spans(), &[
+            Span::new(Location(1, 5, 4), Location(1, 6, 5)),
+            Span::new(Location(1, 12, 11), Location(1, 13, 12)),
+        ]);
+    }
+
+    #[test]
+    fn minus_without_number_is_not_a_literal() {
+        let mut parser = Parser::new("- foo".as_bytes(), "test");
+        assert_eq!(parser.parse_standalone_expression(), Err(Error::UnexpectedTokenError {
+            text: "-".to_string(),
+            location: Location(1, 1, 0),
+        }));
+    }
+
+    #[test]
+    fn shares_names_through_the_interner() {
+        let mut parser = Parser::new("Test = ( run = ( ^ self run: run ) run: x = ( ^ x ) )".as_bytes(), "test");
+        let class = parser.parse_class().unwrap();
+        let run = name(&parser, "run");
+        assert_eq!(class.instance_methods[&run].name(), run);
+
+        let mut parser = Parser::new("Other = ( run = ( ) )".as_bytes(), "test").with_interner(parser.into_interner());
+        let other = parser.parse_class().unwrap();
+        assert_eq!(other.instance_methods[&run].name(), class.instance_methods[&run].name());
+        assert_eq!(other.name, name(&parser, "Other"));
+    }
 }
@@ -0,0 +1,756 @@
+use compiler::ast::{Block, Class, Expression, ExpressionKind, Location, Method, MethodTable, Name, Superclass};
+use compiler::interner::Interner;
+use compiler::lexer::escape_string;
+use compiler::ParserOptions;
+use std::slice;
+
+const INDENT: &str = "    ";
+const OPERATORS: &str = "~&|*/\\+=><,@%";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    RecoveredExpressionError { location: Location },
+    NonFiniteDoubleError { value: f64, location: Location },
+}
+
+impl Error {
+    pub fn location(&self) -> Location {
+        match *self {
+            Error::RecoveredExpressionError { location } |
+            Error::NonFiniteDoubleError { location, .. } => location,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, PartialOrd)]
+enum Precedence {
+    Primary,
+    Unary,
+    Binary,
+    Keyword,
+    Cascade,
+    Assignment,
+}
+
+fn precedence(expression: &Expression) -> Precedence {
+    match expression.kind {
+        ExpressionKind::Assignment { .. } | ExpressionKind::Return(_) => Precedence::Assignment,
+        ExpressionKind::Cascade { .. } => Precedence::Cascade,
+        ExpressionKind::KeywordMessage { .. } => Precedence::Keyword,
+        ExpressionKind::BinaryMessage { .. } => Precedence::Binary,
+        ExpressionKind::UnaryMessage { .. } => Precedence::Unary,
+        _ => Precedence::Primary,
+    }
+}
+
+fn receiver_precedence(arguments: &[Expression]) -> Precedence {
+    if arguments.is_empty() {
+        Precedence::Unary
+    } else {
+        Precedence::Binary
+    }
+}
+
+fn is_plain_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic()) && chars.all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_symbol_name(text: &str) -> bool {
+    if let Some(keywords) = text.strip_suffix(':') {
+        keywords.split(':').all(is_plain_identifier)
+    } else if is_plain_identifier(text) {
+        text != "primitive"
+    } else if text == "-" {
+        true
+    } else {
+        !text.is_empty() && text.chars().all(|c| OPERATORS.contains(c))
+    }
+}
+
+fn format_double(value: f64) -> Option<String> {
+    if !value.is_finite() {
+        return None;
+    }
+
+    let text = format!("{:?}", value);
+    match text.find('e') {
+        Some(index) if !text[..index].contains('.') => Some(format!("{}.0{}", &text[..index], &text[index..])),
+        _ => Some(text),
+    }
+}
+
+pub fn print_class(class: &Class, interner: &Interner, options: &ParserOptions) -> Result<String, Error> {
+    let mut printer = Printer::new(interner, options);
+    printer.class(class);
+    printer.finish()
+}
+
+pub fn print_method(method: &Method, interner: &Interner, options: &ParserOptions) -> Result<String, Error> {
+    let mut printer = Printer::new(interner, options);
+    printer.method(method);
+    printer.finish()
+}
+
+pub fn print_expression(expression: &Expression, interner: &Interner, options: &ParserOptions) -> Result<String, Error> {
+    let mut printer = Printer::new(interner, options);
+    printer.expression(expression, Precedence::Assignment);
+    printer.finish()
+}
+
+struct Printer<'a> {
+    output: String,
+    indent: usize,
+    interner: &'a Interner,
+    options: ParserOptions,
+    error: Option<Error>,
+}
+
+impl<'a> Printer<'a> {
+    fn new(interner: &'a Interner, options: &ParserOptions) -> Printer<'a> {
+        Printer { output: String::new(), indent: 0, interner, options: options.clone(), error: None }
+    }
+
+    fn finish(self) -> Result<String, Error> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.output),
+        }
+    }
+
+    fn fail(&mut self, error: Error) {
+        self.error = self.error.take().or(Some(error));
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn name(&mut self, name: Name) {
+        let interner = self.interner;
+        self.write(interner.resolve(name));
+    }
+
+    fn start_line(&mut self) {
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    fn comments(&mut self, comments: &[String]) {
+        for comment in comments {
+            self.start_line();
+            self.write("\"");
+            self.write(comment);
+            self.write("\"\n");
+        }
+    }
+
+    fn names(&mut self, names: &[Name]) {
+        self.write("|");
+        for &name in names {
+            self.write(" ");
+            self.name(name);
+        }
+
+        self.write(" |");
+    }
+
+    fn string(&mut self, text: &str) {
+        if self.options.string_escapes {
+            self.write(&escape_string(text));
+        } else {
+            self.write("'");
+            self.write(&text.replace('\'', "''"));
+            self.write("'");
+        }
+    }
+
+    fn class(&mut self, class: &Class) {
+        self.comments(&class.comments);
+        self.name(class.name);
+        match class.superclass {
+            ref superclass if *superclass == self.options.default_superclass => self.write(" = (\n"),
+            Superclass::None => self.write(" = nil (\n"),
+            Superclass::Implicit => self.write(" = Object (\n"),
+            Superclass::Named(name) => {
+                self.write(" = ");
+                self.name(name);
+                self.write(" (\n");
+            }
+        }
+
+        self.indent += 1;
+        let mut separate = false;
+        self.class_side(&class.instance_variables, &class.instance_methods, &mut separate);
+        if !class.class_variables.is_empty() || !class.class_methods.is_empty() {
+            if separate {
+                self.write("\n");
+            }
+
+            self.start_line();
+            self.write(&"-".repeat(self.options.separator_length));
+            self.write("\n");
+            self.class_side(&class.class_variables, &class.class_methods, &mut true);
+        }
+
+        self.indent -= 1;
+        self.write(")\n");
+    }
+
+    fn class_side(&mut self, variables: &[Name], methods: &MethodTable, separate: &mut bool) {
+        if !variables.is_empty() {
+            if *separate {
+                self.write("\n");
+            }
+
+            self.start_line();
+            self.names(variables);
+            self.write("\n");
+            *separate = true;
+        }
+
+        for method in methods {
+            if *separate {
+                self.write("\n");
+            }
+
+            self.method(method);
+            *separate = true;
+        }
+    }
+
+    fn method(&mut self, method: &Method) {
+        self.comments(method.comments());
+        self.start_line();
+        match *method {
+            Method::Primitive { name, ref parameters, .. } => {
+                self.pattern(name, parameters);
+                self.write(" = primitive\n");
+            }
+            Method::Native { name, body: Block { ref parameters, ref locals, ref body, .. }, .. } => {
+                self.pattern(name, parameters);
+                if locals.is_empty() && body.is_empty() {
+                    self.write(" = ( )\n");
+                    return;
+                }
+
+                self.write(" = (\n");
+                self.indent += 1;
+                if !locals.is_empty() {
+                    self.start_line();
+                    self.names(locals);
+                    self.write("\n");
+                }
+
+                for (index, statement) in body.iter().enumerate() {
+                    self.start_line();
+                    self.expression(statement, Precedence::Assignment);
+                    if index + 1 < body.len() {
+                        self.write(".");
+                    }
+
+                    self.write("\n");
+                }
+
+                self.indent -= 1;
+                self.start_line();
+                self.write(")\n");
+            }
+        }
+    }
+
+    fn pattern(&mut self, name: Name, parameters: &[Name]) {
+        let name = self.interner.resolve(name);
+        if parameters.is_empty() {
+            self.write(name);
+        } else if name.ends_with(':') {
+            for (index, (part, parameter)) in name.split_terminator(':').zip(parameters).enumerate() {
+                if index > 0 {
+                    self.write(" ");
+                }
+
+                self.write(part);
+                self.write(": ");
+                self.name(*parameter);
+            }
+        } else {
+            self.write(name);
+            self.write(" ");
+            self.name(parameters[0]);
+        }
+    }
+
+    fn message(&mut self, selector: Name, arguments: &[Expression]) {
+        let selector = self.interner.resolve(selector);
+        if arguments.is_empty() {
+            self.write(" ");
+            self.write(selector);
+        } else if selector.ends_with(':') {
+            for (part, argument) in selector.split_terminator(':').zip(arguments) {
+                self.write(" ");
+                self.write(part);
+                self.write(": ");
+                self.expression(argument, Precedence::Binary);
+            }
+        } else {
+            self.write(" ");
+            self.write(selector);
+            self.write(" ");
+            self.expression(&arguments[0], Precedence::Unary);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression, limit: Precedence) {
+        if precedence(expression) > limit {
+            self.write("(");
+            self.expression(expression, Precedence::Assignment);
+            self.write(")");
+            return;
+        }
+
+        match expression.kind {
+            ExpressionKind::Assignment { ref variables, ref value, .. } => {
+                for &variable in variables {
+                    self.name(variable);
+                    self.write(" := ");
+                }
+
+                self.expression(value, Precedence::Cascade);
+            }
+            ExpressionKind::BinaryMessage { message, ref left, ref right } => {
+                self.expression(left, Precedence::Binary);
+                self.message(message, slice::from_ref(&**right));
+            }
+            ExpressionKind::Block(Block { ref parameters, ref locals, ref body, .. }) => {
+                self.write("[");
+                for &parameter in parameters {
+                    self.write(" :");
+                    self.name(parameter);
+                }
+
+                if !parameters.is_empty() {
+                    self.write(" |");
+                }
+
+                if !locals.is_empty() {
+                    self.write(" ");
+                    self.names(locals);
+                }
+
+                for (index, statement) in body.iter().enumerate() {
+                    self.write(if index > 0 { ". " } else { " " });
+                    self.expression(statement, Precedence::Assignment);
+                }
+
+                self.write(" ]");
+            }
+            ExpressionKind::Cascade { ref receiver, ref messages } => {
+                let limit = messages.first().map_or(Precedence::Unary, |first| receiver_precedence(&first.arguments));
+                self.expression(receiver, limit);
+                for (index, message) in messages.iter().enumerate() {
+                    if index > 0 {
+                        self.write(";");
+                    }
+
+                    self.message(message.selector, &message.arguments);
+                }
+            }
+            ExpressionKind::KeywordMessage { message, ref receiver, ref parameters } => {
+                self.expression(receiver, Precedence::Binary);
+                self.message(message, parameters);
+            }
+            ExpressionKind::Error => self.fail(Error::RecoveredExpressionError { location: expression.span.start }),
+            ExpressionKind::LiteralNil => self.write("nil"),
+            ExpressionKind::LiteralArray(ref values) => {
+                self.write("#(");
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        self.write(" ");
+                    }
+
+                    self.expression(value, Precedence::Primary);
+                }
+
+                self.write(")");
+            }
+            ExpressionKind::LiteralBigInteger(ref value) => self.write(&value.to_string()),
+            ExpressionKind::LiteralBoolean(value) => self.write(if value { "true" } else { "false" }),
+            ExpressionKind::LiteralCharacter(value) => {
+                self.write("$");
+                self.output.push(value);
+            }
+            ExpressionKind::LiteralDouble(value) => match format_double(value) {
+                Some(text) => self.write(&text),
+                None => self.fail(Error::NonFiniteDoubleError { value, location: expression.span.start }),
+            },
+            ExpressionKind::LiteralInteger(value) => self.write(&value.to_string()),
+            ExpressionKind::LiteralString(ref value) => self.string(value),
+            ExpressionKind::LiteralSymbol(value) => {
+                let value = self.interner.resolve(value);
+                self.write("#");
+                if is_symbol_name(value) {
+                    self.write(value);
+                } else {
+                    self.string(value);
+                }
+            }
+            ExpressionKind::Return(ref value) => {
+                self.write("^ ");
+                self.expression(value, Precedence::Assignment);
+            }
+            ExpressionKind::UnaryMessage { message, ref receiver } => {
+                self.expression(receiver, Precedence::Unary);
+                self.message(message, &[]);
+            }
+            ExpressionKind::Variable(name) => self.name(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{print_class, print_expression, print_method, Error};
+    use compiler::ast::{Block, Class, Expression, ExpressionKind, Location, Method, MethodTable, Name, Span, Superclass};
+    use compiler::{Dialect, Interner, Parser, ParserOptions};
+    use num_bigint::BigInt;
+    use quickcheck::{Arbitrary, Gen, QuickCheck};
+
+    const IDENTIFIERS: [&str; 8] = ["a", "b", "foo", "bar", "x1", "value", "each", "Item"];
+    const BINARY_SELECTORS: [&str; 12] = ["+", "-", "*", "/", "<=", "==", "||", ",", "@", "~=", "&", "<"];
+    const SYMBOLS: [&str; 8] = ["foo", "at:put:", "with:", "+", "-", "primitive", "two words", "a_b:c:"];
+    const DIALECTS: [Dialect; 4] = [Dialect::SomJava, Dialect::SomCpp, Dialect::TruffleSom, Dialect::SomNsLite];
+
+    fn print_parsed_method(source: &str) -> String {
+        let mut parser = Parser::new(source.as_bytes(), "test");
+        let method = parser.parse_standalone_method().unwrap();
+        print_method(&method, parser.interner(), &ParserOptions::default()).unwrap()
+    }
+
+    fn round_trip(source: &str) -> String {
+        let mut parser = Parser::new(source.as_bytes(), "test");
+        let expression = parser.parse_standalone_expression().unwrap();
+        print_expression(&expression, parser.interner(), &ParserOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn parenthesises_by_precedence() {
+        assert_eq!(round_trip("(a foo: b) bar"), "(a foo: b) bar");
+        assert_eq!(round_trip("a foo: (b bar: c)"), "a foo: (b bar: c)");
+        assert_eq!(round_trip("a + (b + c)"), "a + (b + c)");
+        assert_eq!(round_trip("(a + b) + c"), "a + b + c");
+        assert_eq!(round_trip("(a + b) foo"), "(a + b) foo");
+        assert_eq!(round_trip("a foo: b + c bar"), "a foo: b + c bar");
+        assert_eq!(round_trip("((a))"), "a");
+    }
+
+    #[test]
+    fn prints_assignments() {
+        assert_eq!(round_trip("a := b := 3 + 4"), "a := b := 3 + 4");
+        assert_eq!(round_trip("a := (b := 3)"), "a := (b := 3)");
+    }
+
+    #[test]
+    fn prints_literals() {
+        assert_eq!(round_trip("#(1 -2 3.5 #foo 'b''c' $a #(nested) true)"), "#(1 -2 3.5 #foo 'b\\'c' $a #(#nested) true)");
+        assert_eq!(round_trip("#'two words'"), "#'two words'");
+        assert_eq!(round_trip("#at:put:"), "#at:put:");
+        assert_eq!(round_trip("1e20"), "100000000000000000000");
+        assert_eq!(round_trip("1.0e300"), "1.0e300");
+        assert_eq!(round_trip("a - -1"), "a - -1");
+    }
+
+    #[test]
+    fn prints_cascades() {
+        let options = ParserOptions { cascades: true, ..Default::default() };
+        let source = "Test = ( run = ( x := (a foo: 1) bar; + 2; at: 3 put: (b c: d); yourself ) )";
+        let mut parser = Parser::new(source.as_bytes(), "test").with_options(options.clone());
+        let class = parser.parse_class().unwrap();
+        let printed = print_class(&class, parser.interner(), &options).unwrap();
+        assert!(printed.contains("        x := (a foo: 1) bar; + 2; at: 3 put: (b c: d); yourself\n"));
+
+        let reparsed = Parser::new(printed.as_bytes(), "test").with_options(options).with_interner(parser.into_interner()).parse_class().unwrap();
+        assert_eq!(reparsed.without_spans(), class.without_spans());
+    }
+
+    #[test]
+    fn prints_blocks() {
+        assert_eq!(round_trip("[]"), "[ ]");
+        assert_eq!(round_trip("[:a :b | |t| t := a. ^ t + b]"), "[ :a :b | | t | t := a. ^ t + b ]");
+    }
+
+    #[test]
+    fn prints_methods() {
+        let source = "at: index put: value = ( | old | old := self at: index. ^ old )";
+        assert_eq!(print_parsed_method(source), "at: index put: value = (\n    | old |\n    old := self at: index.\n    ^ old\n)\n");
+
+        assert_eq!(print_parsed_method("+ other = primitive"), "+ other = primitive\n");
+    }
+
+    #[test]
+    fn prints_classes() {
+        let source = "\"Doc\" Hello = Base ( | a b | \"Runs\" run = ( ^ a ) empty = ( ) ---- | c | new = primitive )";
+        let mut parser = Parser::new(source.as_bytes(), "test").with_trivia();
+        let class = parser.parse_class().unwrap();
+        assert_eq!(print_class(&class, parser.interner(), &ParserOptions::default()).unwrap(), "\
+\"Doc\"
+Hello = Base (
+    | a b |
+
+    \"Runs\"
+    run = (
+        ^ a
+    )
+
+    empty = ( )
+
+    ----
+
+    | c |
+
+    new = primitive
+)
+");
+    }
+
+    #[test]
+    fn rejects_unprintable_expressions() {
+        let source = "Test = ( run = ( ^ 1 + . ) )";
+        let mut parser = Parser::new(source.as_bytes(), "test");
+        let (class, errors) = parser.parse_class_with_recovery();
+        assert!(!errors.is_empty());
+        assert_eq!(print_class(&class.unwrap(), parser.interner(), &ParserOptions::default()), Err(Error::RecoveredExpressionError { location: Location(1, 18, 17) }));
+
+        for &value in &[f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            let result = print_expression(&ExpressionKind::LiteralDouble(value).into(), &Interner::new(), &ParserOptions::default());
+            assert_eq!(result.map_err(|error| error.location()), Err(Location::default()));
+        }
+    }
+
+    #[test]
+    fn prints_in_every_dialect() {
+        for &dialect in &DIALECTS {
+            let options = dialect.options();
+            let source = format!("Test = ( run = ( ^ 'a\\b''c' , #'d\\t' ) {} | count | )", "-".repeat(options.separator_length));
+            let mut parser = Parser::new(source.as_bytes(), "test").with_options(options.clone());
+            let class = parser.parse_class().unwrap();
+            let printed = print_class(&class, parser.interner(), &options).unwrap();
+            assert_eq!(printed.contains(r"^ 'a\b''c' , #'d\t'"), !options.string_escapes, "{:?}:\n{}", dialect, printed);
+
+            let reparsed = Parser::new(printed.as_bytes(), "test").with_options(options).with_interner(parser.into_interner()).parse_class().unwrap();
+            assert_eq!(reparsed.without_spans(), class.without_spans(), "{:?}:\n{}", dialect, printed);
+        }
+    }
+
+    #[test]
+    fn prints_implicit_superclass_explicitly_when_not_the_default() {
+        let mut parser = Parser::new("Test = ( )".as_bytes(), "test");
+        let class = parser.parse_class().unwrap();
+        assert_eq!(class.superclass, Superclass::Implicit);
+        assert_eq!(print_class(&class, parser.interner(), &ParserOptions::default()).unwrap(), "Test = (\n)\n");
+
+        let options = Dialect::SomNsLite.options();
+        let printed = print_class(&class, parser.interner(), &options).unwrap();
+        assert_eq!(printed, "Test = Object (\n)\n");
+        let mut interner = parser.into_interner();
+        let reparsed = Parser::new(printed.as_bytes(), "test").with_options(options).with_interner(interner.clone()).parse_class().unwrap();
+        assert_eq!(reparsed.superclass.name(&mut interner), Some(interner.intern("Object")));
+    }
+
+    #[derive(Clone, Debug)]
+    struct ArbitraryClass(Class, Interner);
+
+    #[derive(Clone, Debug)]
+    struct ArbitraryDialect(Dialect);
+
+    fn choose<T: Copy>(g: &mut Gen, values: &[T]) -> T {
+        *g.choose(values).unwrap()
+    }
+
+    fn name(g: &mut Gen, interner: &mut Interner) -> Name {
+        interner.intern(choose(g, &IDENTIFIERS))
+    }
+
+    fn distinct_names(g: &mut Gen, interner: &mut Interner, limit: usize) -> Vec<Name> {
+        let mut names = vec![];
+        for _ in 0..usize::arbitrary(g) % (limit + 1) {
+            let name = name(g, interner);
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        names
+    }
+
+    fn comments(g: &mut Gen) -> Vec<String> {
+        let count = usize::arbitrary(g) % 3;
+        (0..count).map(|_| choose(g, &["Doc", "Two\nlines", "it's"]).to_string()).collect()
+    }
+
+    fn literal(g: &mut Gen, interner: &mut Interner, depth: usize) -> ExpressionKind {
+        match u8::arbitrary(g) % if depth == 0 { 9 } else { 10 } {
+            0 => ExpressionKind::LiteralInteger(i64::arbitrary(g)),
+            1 => {
+                let value = BigInt::from(i64::MAX) + u32::arbitrary(g) + 2;
+                ExpressionKind::LiteralBigInteger(if bool::arbitrary(g) { value } else { -value })
+            }
+            2 => {
+                let value = f64::arbitrary(g);
+                ExpressionKind::LiteralDouble(if value.is_finite() { value } else { 0.5 })
+            }
+            3 => ExpressionKind::LiteralString(String::arbitrary(g)),
+            4 => ExpressionKind::LiteralSymbol(interner.intern(choose(g, &SYMBOLS))),
+            5 => ExpressionKind::LiteralCharacter(choose(g, &['a', ' ', '\'', '$', '"', 'é'])),
+            6 => ExpressionKind::LiteralBoolean(bool::arbitrary(g)),
+            7 => ExpressionKind::LiteralNil,
+            8 => ExpressionKind::LiteralInteger(i64::from(u8::arbitrary(g))),
+            _ => {
+                let count = usize::arbitrary(g) % 4;
+                ExpressionKind::LiteralArray((0..count).map(|_| literal(g, interner, depth - 1).into()).collect())
+            }
+        }
+    }
+
+    fn statements(g: &mut Gen, interner: &mut Interner, depth: usize) -> Vec<Expression> {
+        let count = usize::arbitrary(g) % 4;
+        (0..count).map(|index| {
+            let value = expression(g, interner, depth);
+            if index + 1 == count && bool::arbitrary(g) {
+                ExpressionKind::Return(Box::new(value)).into()
+            } else {
+                value
+            }
+        }).collect()
+    }
+
+    fn expression(g: &mut Gen, interner: &mut Interner, depth: usize) -> Expression {
+        let choice = if depth == 0 { u8::arbitrary(g) % 2 } else { u8::arbitrary(g) % 8 };
+        let kind = match choice {
+            0 => ExpressionKind::Variable(name(g, interner)),
+            1 => literal(g, interner, depth),
+            2 => ExpressionKind::UnaryMessage {
+                message: name(g, interner),
+                receiver: Box::new(expression(g, interner, depth - 1)),
+            },
+            3 => ExpressionKind::BinaryMessage {
+                message: interner.intern(choose(g, &BINARY_SELECTORS)),
+                left: Box::new(expression(g, interner, depth - 1)),
+                right: Box::new(expression(g, interner, depth - 1)),
+            },
+            4 => {
+                let count = 1 + usize::arbitrary(g) % 3;
+                let parts: Vec<_> = (0..count).map(|_| format!("{}:", choose(g, &IDENTIFIERS))).collect();
+                ExpressionKind::KeywordMessage {
+                    message: interner.intern(&parts.concat()),
+                    receiver: Box::new(expression(g, interner, depth - 1)),
+                    parameters: (0..count).map(|_| expression(g, interner, depth - 1)).collect(),
+                }
+            }
+            5 => {
+                let variables: Vec<_> = distinct_names(g, interner, 2).into_iter().chain(Some(name(g, interner))).collect();
+                ExpressionKind::Assignment {
+                    variable_spans: vec![Span::default(); variables.len()],
+                    variables,
+                    value: Box::new(expression(g, interner, depth - 1)),
+                }
+            }
+            6 => {
+                let parameters = distinct_names(g, interner, 2);
+                let parameter_spans = vec![Span::default(); parameters.len()];
+                let locals = distinct_names(g, interner, 2);
+                ExpressionKind::Block(Block::new(parameters, parameter_spans, locals, statements(g, interner, depth - 1), Span::default()))
+            }
+            _ => literal(g, interner, depth),
+        };
+
+        kind.into()
+    }
+
+    fn method(g: &mut Gen, interner: &mut Interner) -> Method {
+        let (name, parameters) = match u8::arbitrary(g) % 3 {
+            0 => (name(g, interner), vec![]),
+            1 => (interner.intern(choose(g, &BINARY_SELECTORS)), vec![name(g, interner)]),
+            _ => {
+                let parameters = distinct_names(g, interner, 3);
+                let parts: Vec<_> = parameters.iter().map(|_| format!("{}:", choose(g, &IDENTIFIERS))).collect();
+                match parts.is_empty() {
+                    true => (name(g, interner), vec![]),
+                    false => (interner.intern(&parts.concat()), parameters),
+                }
+            }
+        };
+
+        let parameter_spans = vec![Span::default(); parameters.len()];
+        if bool::arbitrary(g) {
+            Method::Primitive { name, parameters, parameter_spans, comments: comments(g), span: Span::default() }
+        } else {
+            let depth = usize::arbitrary(g) % 4;
+            let locals = distinct_names(g, interner, 3);
+            Method::Native {
+                name,
+                body: Block::new(parameters, parameter_spans, locals, statements(g, interner, depth), Span::default()),
+                comments: comments(g),
+                span: Span::default(),
+            }
+        }
+    }
+
+    impl Arbitrary for ArbitraryClass {
+        fn arbitrary(g: &mut Gen) -> ArbitraryClass {
+            let mut interner = Interner::new();
+            let methods = |g: &mut Gen, interner: &mut Interner| {
+                let mut table = MethodTable::new();
+                for _ in 0..usize::arbitrary(g) % 4 {
+                    let _ = table.insert(method(g, interner));
+                }
+
+                table
+            };
+            let class = Class {
+                name: interner.intern(choose(g, &["Hello", "Counter"])),
+                superclass: match u8::arbitrary(g) % 3 {
+                    0 => Superclass::None,
+                    1 => Superclass::Implicit,
+                    _ => Superclass::Named(interner.intern(choose(g, &["Object", "Array"]))),
+                },
+                instance_methods: methods(g, &mut interner),
+                instance_variables: distinct_names(g, &mut interner, 3),
+                class_methods: methods(g, &mut interner),
+                class_variables: distinct_names(g, &mut interner, 2),
+                comments: comments(g),
+                span: Span::default(),
+            };
+            ArbitraryClass(class, interner)
+        }
+    }
+
+    impl Arbitrary for ArbitraryDialect {
+        fn arbitrary(g: &mut Gen) -> ArbitraryDialect {
+            ArbitraryDialect(choose(g, &DIALECTS))
+        }
+    }
+
+    #[test]
+    fn parse_print_round_trip() {
+        fn property(class: ArbitraryClass, dialect: ArbitraryDialect) -> bool {
+            let (ArbitraryClass(class, interner), ArbitraryDialect(dialect)) = (class, dialect);
+            let options = dialect.options();
+            let source = print_class(&class, &interner, &options).unwrap();
+            let mut parser = Parser::new(source.as_bytes(), "test").with_options(options).with_interner(interner).with_trivia();
+            let parsed = parser.parse_class();
+            let mut interner = parser.into_interner();
+            match parsed {
+                Ok(ref parsed) if parsed.superclass.name(&mut interner) == class.superclass.name(&mut interner) => {
+                    Class { superclass: class.superclass.clone(), ..parsed.without_spans() } == class
+                }
+                _ => {
+                    eprintln!("{:?}:\n{}\n{:#?}", dialect, source, interner.debug(&parsed));
+                    false
+                }
+            }
+        }
+
+        QuickCheck::new().tests(500).quickcheck(property as fn(ArbitraryClass, ArbitraryDialect) -> bool);
+    }
+}
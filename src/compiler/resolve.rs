@@ -0,0 +1,310 @@
+use compiler::ast::{Block, Class, Expression, ExpressionKind, Location, Method, Name, Span};
+use compiler::grammar::is_reserved_name;
+use compiler::interner::Interner;
+use compiler::visit::{walk_block, walk_expression, Visitor};
+
+const GLOBAL_VARIABLES: [&str; 1] = ["system"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingKind {
+    MethodArgument,
+    MethodLocal,
+    BlockParameter,
+    BlockLocal,
+    InstanceVariable,
+    ClassVariable,
+    Global,
+    Receiver,
+    Super,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Binding {
+    pub kind: BindingKind,
+    pub depth: usize,
+    pub index: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reference {
+    pub name: Name,
+    pub binding: Binding,
+    pub assignment: bool,
+    pub span: Span,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Diagnostic {
+    UndefinedVariableError { name: Name, location: Location },
+    ArgumentAssignmentError { name: Name, location: Location },
+}
+
+impl Diagnostic {
+    pub fn location(&self) -> Location {
+        match *self {
+            Diagnostic::UndefinedVariableError { location, .. } |
+            Diagnostic::ArgumentAssignmentError { location, .. } => location,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Resolution {
+    pub references: Vec<Reference>,
+    pub globals: Vec<Name>,
+    pub errors: Vec<Diagnostic>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Resolver {
+    instance_variables: Vec<Name>,
+    class_variables: Vec<Name>,
+}
+
+struct Scope {
+    parameters: Vec<Name>,
+    locals: Vec<Name>,
+    method: bool,
+}
+
+struct Scopes<'a> {
+    interner: &'a Interner,
+    fields: Vec<Name>,
+    class_side: bool,
+    scopes: Vec<Scope>,
+    resolution: &'a mut Resolution,
+}
+
+impl<'a> Scopes<'a> {
+    fn lookup(&mut self, name: Name) -> Option<Binding> {
+        let method_depth = self.scopes.len() - 1;
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            let (kind, index) = match (scope.parameters.iter().position(|&p| p == name), scope.locals.iter().position(|&l| l == name)) {
+                (Some(index), _) if scope.method => (BindingKind::MethodArgument, index),
+                (Some(index), _) => (BindingKind::BlockParameter, index),
+                (None, Some(index)) if scope.method => (BindingKind::MethodLocal, index),
+                (None, Some(index)) => (BindingKind::BlockLocal, index),
+                (None, None) => continue,
+            };
+
+            return Some(Binding { kind, depth, index });
+        }
+
+        let text = self.interner.resolve(name);
+        let (kind, index) = match text {
+            "self" => (BindingKind::Receiver, 0),
+            "super" => (BindingKind::Super, 0),
+            _ => match self.fields.iter().position(|&field| field == name) {
+                Some(index) if self.class_side => (BindingKind::ClassVariable, index),
+                Some(index) => (BindingKind::InstanceVariable, index),
+                None if text.starts_with(|c: char| c.is_ascii_uppercase()) || GLOBAL_VARIABLES.contains(&text) => {
+                    let globals = &mut self.resolution.globals;
+                    let index = globals.iter().position(|&global| global == name).unwrap_or_else(|| {
+                        globals.push(name);
+                        globals.len() - 1
+                    });
+
+                    return Some(Binding { kind: BindingKind::Global, depth: 0, index });
+                }
+                None => return None,
+            },
+        };
+
+        Some(Binding { kind, depth: method_depth, index })
+    }
+
+    fn reference(&mut self, name: Name, span: Span, assignment: bool) {
+        let binding = match self.lookup(name) {
+            Some(binding) => binding,
+            None => {
+                self.resolution.errors.push(Diagnostic::UndefinedVariableError { name, location: span.start });
+                return;
+            }
+        };
+
+        match binding.kind {
+            BindingKind::MethodArgument | BindingKind::BlockParameter if assignment => {
+                self.resolution.errors.push(Diagnostic::ArgumentAssignmentError { name, location: span.start });
+            }
+            _ => {}
+        }
+
+        self.resolution.references.push(Reference { name, binding, assignment, span });
+    }
+
+    fn enter(&mut self, block: &Block, method: bool) {
+        self.scopes.push(Scope { parameters: block.parameters.clone(), locals: block.locals.clone(), method });
+        walk_block(self, block);
+        self.scopes.pop();
+    }
+}
+
+impl<'a> Visitor for Scopes<'a> {
+    fn visit_method(&mut self, method: &Method) {
+        if let Method::Native { ref body, .. } = *method {
+            self.enter(body, true);
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.enter(block, false);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression.kind {
+            ExpressionKind::Variable(name) => self.reference(name, expression.span, false),
+            ExpressionKind::Assignment { ref variables, ref variable_spans, .. } => {
+                let interner = self.interner;
+                for (&name, &span) in variables.iter().zip(variable_spans).filter(|&(&name, _)| !is_reserved_name(interner.resolve(name))) {
+                    self.reference(name, span, true);
+                }
+            }
+            _ => {}
+        }
+
+        walk_expression(self, expression);
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Default::default()
+    }
+
+    pub fn with_inherited_fields(self, instance_variables: Vec<Name>, class_variables: Vec<Name>) -> Resolver {
+        Resolver { instance_variables, class_variables }
+    }
+
+    pub fn resolve(&self, class: &Class, interner: &Interner) -> Resolution {
+        let mut resolution = Resolution::default();
+        let sides = [
+            (&class.instance_methods, &self.instance_variables, &class.instance_variables, false),
+            (&class.class_methods, &self.class_variables, &class.class_variables, true),
+        ];
+
+        for &(methods, inherited, own, class_side) in &sides {
+            let mut scopes = Scopes {
+                interner,
+                fields: inherited.iter().chain(own).cloned().collect(),
+                class_side,
+                scopes: vec![],
+                resolution: &mut resolution,
+            };
+
+            for method in methods {
+                scopes.visit_method(method);
+            }
+        }
+
+        resolution
+    }
+}
+
+pub fn resolve(class: &Class, interner: &Interner) -> Resolution {
+    Resolver::new().resolve(class, interner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve, Binding, BindingKind, Diagnostic, Resolver};
+    use compiler::ast::Class;
+    use compiler::interner::Interner;
+    use compiler::test_util::parse;
+    use util::location::Location;
+
+    fn bindings(class: &Class, interner: &Interner) -> Vec<(String, BindingKind, usize, usize, bool)> {
+        resolve(class, interner).references.iter()
+            .map(|r| (interner.resolve(r.name).to_string(), r.binding.kind, r.binding.depth, r.binding.index, r.assignment))
+            .collect()
+    }
+
+    #[test]
+    fn classifies_lexical_variables() {
+        let (class, interner) = parse("Test = ( at: i put: v = ( | old | old := i. ^ [ :x | | y | y := x + v. [ old ] ] ) )");
+        assert_eq!(bindings(&class, &interner), vec![
+            ("old".to_string(), BindingKind::MethodLocal, 0, 0, true),
+            ("i".to_string(), BindingKind::MethodArgument, 0, 0, false),
+            ("y".to_string(), BindingKind::BlockLocal, 0, 0, true),
+            ("x".to_string(), BindingKind::BlockParameter, 0, 0, false),
+            ("v".to_string(), BindingKind::MethodArgument, 1, 1, false),
+            ("old".to_string(), BindingKind::MethodLocal, 2, 0, false),
+        ]);
+    }
+
+    #[test]
+    fn inner_scopes_shadow_outer_names() {
+        let (class, interner) = parse("Test = ( | x | run: x = ( ^ [ :x | x ] value: x ) )");
+        assert_eq!(bindings(&class, &interner), vec![
+            ("x".to_string(), BindingKind::BlockParameter, 0, 0, false),
+            ("x".to_string(), BindingKind::MethodArgument, 0, 0, false),
+        ]);
+    }
+
+    #[test]
+    fn classifies_fields_receivers_and_globals() {
+        let (class, interner) = parse("Test = ( | a b | run = ( ^ [ b := self foo: super bar ] ) ---- | count | new = ( count := count + 1. system println: Array new ) )");
+        assert_eq!(bindings(&class, &interner), vec![
+            ("b".to_string(), BindingKind::InstanceVariable, 1, 1, true),
+            ("self".to_string(), BindingKind::Receiver, 1, 0, false),
+            ("super".to_string(), BindingKind::Super, 1, 0, false),
+            ("count".to_string(), BindingKind::ClassVariable, 0, 0, true),
+            ("count".to_string(), BindingKind::ClassVariable, 0, 0, false),
+            ("system".to_string(), BindingKind::Global, 0, 0, false),
+            ("Array".to_string(), BindingKind::Global, 0, 1, false),
+        ]);
+        assert_eq!(resolve(&class, &interner).globals, vec![interner.get("system").unwrap(), interner.get("Array").unwrap()]);
+    }
+
+    #[test]
+    fn inherited_fields_come_first() {
+        let (class, interner) = parse("Test = Base ( | own | run = ( ^ own + base ) )");
+        let resolution = Resolver::new().with_inherited_fields(vec![interner.get("base").unwrap()], vec![]).resolve(&class, &interner);
+        let bindings: Vec<_> = resolution.references.iter().map(|r| r.binding).collect();
+        assert_eq!(bindings, vec![
+            Binding { kind: BindingKind::InstanceVariable, depth: 0, index: 1 },
+            Binding { kind: BindingKind::InstanceVariable, depth: 0, index: 0 },
+        ]);
+        assert!(resolution.errors.is_empty());
+    }
+
+    #[test]
+    fn reports_undefined_variables() {
+        let (class, interner) = parse("Test = ( run = (\n    ^ missing + [ :x | x + other ] value\n) )");
+        let errors = resolve(&class, &interner).errors;
+        assert_eq!(errors, vec![
+            Diagnostic::UndefinedVariableError { name: interner.get("missing").unwrap(), location: Location(2, 7, 23) },
+            Diagnostic::UndefinedVariableError { name: interner.get("other").unwrap(), location: Location(2, 28, 44) },
+        ]);
+        assert_eq!(errors[1].location(), Location(2, 28, 44));
+    }
+
+    #[test]
+    fn reports_assignments_to_arguments() {
+        let (class, interner) = parse("Test = ( run: a = ( a := 1. [ :b | b := a ] value: 2 ) )");
+        assert_eq!(resolve(&class, &interner).errors, vec![
+            Diagnostic::ArgumentAssignmentError { name: interner.get("a").unwrap(), location: Location(1, 21, 20) },
+            Diagnostic::ArgumentAssignmentError { name: interner.get("b").unwrap(), location: Location(1, 36, 35) },
+        ]);
+    }
+
+    #[test]
+    fn reports_each_assignment_target_at_its_own_span() {
+        let (class, interner) = parse("Test = ( run: a with: b = ( | x | a := x := b := 1 ) )");
+        let resolution = resolve(&class, &interner);
+        assert_eq!(resolution.errors, vec![
+            Diagnostic::ArgumentAssignmentError { name: interner.get("a").unwrap(), location: Location(1, 35, 34) },
+            Diagnostic::ArgumentAssignmentError { name: interner.get("b").unwrap(), location: Location(1, 45, 44) },
+        ]);
+        let spans: Vec<_> = resolution.references.iter().map(|r| r.span.start.column()).collect();
+        assert_eq!(spans, vec![35, 40, 45]);
+    }
+
+    #[test]
+    fn leaves_reserved_assignment_targets_to_checks() {
+        let (class, interner) = parse("Test = ( run = ( self := nil := true := super := 1. ^ self ) )");
+        let resolution = resolve(&class, &interner);
+        assert!(resolution.errors.is_empty());
+        assert_eq!(resolution.references.iter().map(|r| r.binding.kind).collect::<Vec<_>>(), vec![BindingKind::Receiver]);
+    }
+}
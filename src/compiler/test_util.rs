@@ -0,0 +1,13 @@
+use compiler::ast::Class;
+use compiler::interner::Interner;
+use compiler::Parser;
+
+pub fn parse(source: &str) -> (Class, Interner) {
+    parse_with(source, Interner::new())
+}
+
+pub fn parse_with(source: &str, interner: Interner) -> (Class, Interner) {
+    let mut parser = Parser::new(source.as_bytes(), "test").with_interner(interner);
+    let class = parser.parse_class().unwrap();
+    (class, parser.into_interner())
+}
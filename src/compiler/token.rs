@@ -1,10 +1,14 @@
+use std::borrow::Cow;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Symbol {
     And,
     Assign,
     At,
+    Character,
     Colon,
     Comma,
+    Comment,
     Divide,
     Double,
     EndBlock,
@@ -30,9 +34,11 @@ pub enum Symbol {
     Plus,
     Pound,
     Primitive,
+    Semicolon,
     Separator,
     Star,
     String,
+    Whitespace,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -43,3 +49,57 @@ impl From<Symbol> for Token {
         Token(symbol, None)
     }
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BorrowedToken<'a>(pub Symbol, pub Option<Cow<'a, str>>);
+
+impl<'a> From<Symbol> for BorrowedToken<'a> {
+    fn from(symbol: Symbol) -> BorrowedToken<'a> {
+        BorrowedToken(symbol, None)
+    }
+}
+
+impl<'a> BorrowedToken<'a> {
+    pub fn into_owned(self) -> Token {
+        let BorrowedToken(symbol, text) = self;
+        Token(symbol, text.map(Cow::into_owned))
+    }
+}
+
+impl Token {
+    pub fn text(&self) -> String {
+        if let Token(_, Some(ref text)) = *self {
+            return text.clone();
+        }
+
+        match self.0 {
+            Symbol::And => "&",
+            Symbol::Assign => ":=",
+            Symbol::At => "@",
+            Symbol::Colon => ":",
+            Symbol::Comma => ",",
+            Symbol::Divide => "/",
+            Symbol::EndBlock => "]",
+            Symbol::EndTerm => ")",
+            Symbol::Equal => "=",
+            Symbol::Exit => "^",
+            Symbol::Less => "<",
+            Symbol::Minus => "-",
+            Symbol::Modulus => "\\",
+            Symbol::More => ">",
+            Symbol::NewBlock => "[",
+            Symbol::NewTerm => "(",
+            Symbol::Not => "~",
+            Symbol::Or => "|",
+            Symbol::Percent => "%",
+            Symbol::Period => ".",
+            Symbol::Plus => "+",
+            Symbol::Pound => "#",
+            Symbol::Primitive => "primitive",
+            Symbol::Semicolon => ";",
+            Symbol::Separator => "----",
+            Symbol::Star => "*",
+            _ => "",
+        }.to_string()
+    }
+}
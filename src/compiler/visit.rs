@@ -0,0 +1,362 @@
+use compiler::ast::{Block, Class, Expression, ExpressionKind, Message, Method, MethodTable};
+use std::mem;
+
+pub trait Visitor {
+    fn visit_class(&mut self, class: &Class) {
+        walk_class(self, class);
+    }
+
+    fn visit_method(&mut self, method: &Method) {
+        walk_method(self, method);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+}
+
+pub trait VisitorMut {
+    fn visit_class_mut(&mut self, class: &mut Class) -> Result<(), Vec<Method>> {
+        walk_class_mut(self, class)
+    }
+
+    fn visit_method_mut(&mut self, method: &mut Method) {
+        walk_method_mut(self, method);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+    }
+}
+
+pub trait Fold {
+    fn fold_class(&mut self, class: Class) -> Result<Class, Vec<Method>> {
+        fold_class(self, class)
+    }
+
+    fn fold_method(&mut self, method: Method) -> Method {
+        fold_method(self, method)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        fold_block(self, block)
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression(self, expression)
+    }
+}
+
+pub fn walk_class<V: Visitor + ?Sized>(visitor: &mut V, class: &Class) {
+    for method in class.instance_methods.iter().chain(&class.class_methods) {
+        visitor.visit_method(method);
+    }
+}
+
+pub fn walk_method<V: Visitor + ?Sized>(visitor: &mut V, method: &Method) {
+    if let Method::Native { ref body, .. } = *method {
+        visitor.visit_block(body);
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    for expression in &block.body {
+        visitor.visit_expression(expression);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression.kind {
+        ExpressionKind::Assignment { ref value, .. } | ExpressionKind::Return(ref value) => visitor.visit_expression(value),
+        ExpressionKind::BinaryMessage { ref left, ref right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        ExpressionKind::Block(ref block) => visitor.visit_block(block),
+        ExpressionKind::Cascade { ref receiver, ref messages } => {
+            visitor.visit_expression(receiver);
+            for argument in messages.iter().flat_map(|message| &message.arguments) {
+                visitor.visit_expression(argument);
+            }
+        }
+        ExpressionKind::LiteralArray(ref values) => {
+            for value in values {
+                visitor.visit_expression(value);
+            }
+        }
+        ExpressionKind::KeywordMessage { ref receiver, ref parameters, .. } => {
+            visitor.visit_expression(receiver);
+            for parameter in parameters {
+                visitor.visit_expression(parameter);
+            }
+        }
+        ExpressionKind::UnaryMessage { ref receiver, .. } => visitor.visit_expression(receiver),
+        _ => {}
+    }
+}
+
+pub fn walk_class_mut<V: VisitorMut + ?Sized>(visitor: &mut V, class: &mut Class) -> Result<(), Vec<Method>> {
+    let instance_side = class.instance_methods.for_each_mut(|method| visitor.visit_method_mut(method));
+    let class_side = class.class_methods.for_each_mut(|method| visitor.visit_method_mut(method));
+    match (instance_side, class_side) {
+        (Ok(()), Ok(())) => Ok(()),
+        (Err(duplicates), Ok(())) | (Ok(()), Err(duplicates)) => Err(duplicates),
+        (Err(mut duplicates), Err(class_duplicates)) => {
+            duplicates.extend(class_duplicates);
+            Err(duplicates)
+        }
+    }
+}
+
+pub fn walk_method_mut<V: VisitorMut + ?Sized>(visitor: &mut V, method: &mut Method) {
+    if let Method::Native { ref mut body, .. } = *method {
+        visitor.visit_block_mut(body);
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut Block) {
+    for expression in &mut block.body {
+        visitor.visit_expression_mut(expression);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match expression.kind {
+        ExpressionKind::Assignment { ref mut value, .. } | ExpressionKind::Return(ref mut value) => {
+            visitor.visit_expression_mut(value)
+        }
+        ExpressionKind::BinaryMessage { ref mut left, ref mut right, .. } => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(right);
+        }
+        ExpressionKind::Block(ref mut block) => visitor.visit_block_mut(block),
+        ExpressionKind::Cascade { ref mut receiver, ref mut messages } => {
+            visitor.visit_expression_mut(receiver);
+            for argument in messages.iter_mut().flat_map(|message| &mut message.arguments) {
+                visitor.visit_expression_mut(argument);
+            }
+        }
+        ExpressionKind::LiteralArray(ref mut values) => {
+            for value in values {
+                visitor.visit_expression_mut(value);
+            }
+        }
+        ExpressionKind::KeywordMessage { ref mut receiver, ref mut parameters, .. } => {
+            visitor.visit_expression_mut(receiver);
+            for parameter in parameters {
+                visitor.visit_expression_mut(parameter);
+            }
+        }
+        ExpressionKind::UnaryMessage { ref mut receiver, .. } => visitor.visit_expression_mut(receiver),
+        _ => {}
+    }
+}
+
+pub fn fold_class<F: Fold + ?Sized>(folder: &mut F, class: Class) -> Result<Class, Vec<Method>> {
+    let instance_methods = MethodTable::from_methods(class.instance_methods.into_iter().map(|method| folder.fold_method(method)));
+    let class_methods = MethodTable::from_methods(class.class_methods.into_iter().map(|method| folder.fold_method(method)));
+    match (instance_methods, class_methods) {
+        (Ok(instance_methods), Ok(class_methods)) => Ok(Class { instance_methods, class_methods, ..class }),
+        (Err(duplicates), Ok(_)) | (Ok(_), Err(duplicates)) => Err(duplicates),
+        (Err(mut duplicates), Err(class_duplicates)) => {
+            duplicates.extend(class_duplicates);
+            Err(duplicates)
+        }
+    }
+}
+
+pub fn fold_method<F: Fold + ?Sized>(folder: &mut F, method: Method) -> Method {
+    match method {
+        Method::Native { name, body, comments, span } => Method::Native {
+            name,
+            body: folder.fold_block(body),
+            comments,
+            span,
+        },
+        method => method,
+    }
+}
+
+pub fn fold_block<F: Fold + ?Sized>(folder: &mut F, block: Block) -> Block {
+    Block {
+        body: fold_all(folder, block.body),
+        ..block
+    }
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    let kind = match expression.kind {
+        ExpressionKind::Assignment { variables, variable_spans, value } => ExpressionKind::Assignment {
+            variables,
+            variable_spans,
+            value: fold_boxed(folder, value),
+        },
+        ExpressionKind::BinaryMessage { message, left, right } => ExpressionKind::BinaryMessage {
+            message,
+            left: fold_boxed(folder, left),
+            right: fold_boxed(folder, right),
+        },
+        ExpressionKind::Block(block) => ExpressionKind::Block(folder.fold_block(block)),
+        ExpressionKind::Cascade { receiver, messages } => ExpressionKind::Cascade {
+            receiver: fold_boxed(folder, receiver),
+            messages: messages.into_iter().map(|message| Message {
+                arguments: fold_all(folder, message.arguments),
+                ..message
+            }).collect(),
+        },
+        ExpressionKind::KeywordMessage { message, receiver, parameters } => ExpressionKind::KeywordMessage {
+            message,
+            receiver: fold_boxed(folder, receiver),
+            parameters: fold_all(folder, parameters),
+        },
+        ExpressionKind::LiteralArray(values) => ExpressionKind::LiteralArray(fold_all(folder, values)),
+        ExpressionKind::Return(value) => ExpressionKind::Return(fold_boxed(folder, value)),
+        ExpressionKind::UnaryMessage { message, receiver } => ExpressionKind::UnaryMessage {
+            message,
+            receiver: fold_boxed(folder, receiver),
+        },
+        kind => kind,
+    };
+
+    Expression::new(kind, expression.span)
+}
+
+fn fold_boxed<F: Fold + ?Sized>(folder: &mut F, mut expression: Box<Expression>) -> Box<Expression> {
+    let value = mem::replace(&mut *expression, ExpressionKind::Error.into());
+    *expression = folder.fold_expression(value);
+    expression
+}
+
+fn fold_all<F: Fold + ?Sized>(folder: &mut F, expressions: Vec<Expression>) -> Vec<Expression> {
+    expressions.into_iter().map(|expression| folder.fold_expression(expression)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fold_expression, walk_expression, walk_expression_mut, Fold, Visitor, VisitorMut};
+    use compiler::ast::{Class, Expression, ExpressionKind, Method, Name};
+    use compiler::test_util::{parse, parse_with};
+
+    fn body(class: &Class, name: Name) -> Vec<Expression> {
+        match class.instance_methods[&name] {
+            Method::Native { ref body, .. } => body.without_spans().body,
+            ref method => panic!("unexpected method {:?}", method),
+        }
+    }
+
+    #[derive(Default)]
+    struct Sends(Vec<Name>);
+
+    impl Visitor for Sends {
+        fn visit_expression(&mut self, expression: &Expression) {
+            match expression.kind {
+                ExpressionKind::UnaryMessage { message, .. }
+                | ExpressionKind::BinaryMessage { message, .. }
+                | ExpressionKind::KeywordMessage { message, .. } => self.0.push(message),
+                _ => {}
+            }
+
+            walk_expression(self, expression);
+        }
+    }
+
+    struct Rename(Name, Name);
+
+    impl VisitorMut for Rename {
+        fn visit_expression_mut(&mut self, expression: &mut Expression) {
+            if let ExpressionKind::Variable(ref mut name) = expression.kind {
+                if *name == self.0 {
+                    *name = self.1;
+                }
+            }
+
+            walk_expression_mut(self, expression);
+        }
+    }
+
+    struct RenameMethod(Name, Name);
+
+    impl VisitorMut for RenameMethod {
+        fn visit_method_mut(&mut self, method: &mut Method) {
+            if let Method::Native { ref mut name, .. } = *method {
+                if *name == self.0 {
+                    *name = self.1;
+                }
+            }
+        }
+    }
+
+    struct ConstantFolder(Name);
+
+    impl Fold for ConstantFolder {
+        fn fold_expression(&mut self, expression: Expression) -> Expression {
+            let expression = fold_expression(self, expression);
+            if let ExpressionKind::BinaryMessage { ref message, ref left, ref right } = expression.kind {
+                if let (&ExpressionKind::LiteralInteger(left), &ExpressionKind::LiteralInteger(right)) = (&left.kind, &right.kind) {
+                    if *message == self.0 {
+                        return Expression::new(ExpressionKind::LiteralInteger(left + right), expression.span);
+                    }
+                }
+            }
+
+            expression
+        }
+    }
+
+    #[test]
+    fn visitor_sees_nested_sends() {
+        let (class, interner) = parse("Test = ( run = ( ^ a foo: [ b bar + 1 ] with: #(1) ) ---- new = ( ^ self baz ) )");
+        let mut sends = Sends::default();
+        sends.visit_class(&class);
+        let sends: Vec<_> = sends.0.into_iter().map(|name| interner.resolve(name)).collect();
+        assert_eq!(sends, vec!["foo:with:", "+", "bar", "baz"]);
+    }
+
+    #[test]
+    fn mutable_visitor_rewrites_in_place() {
+        let (mut class, mut interner) = parse("Test = ( run = ( a := a + [ :b | a ] value ) )");
+        Rename(interner.get("a").unwrap(), interner.intern("z")).visit_class_mut(&mut class).unwrap();
+        let (expected, interner) = parse_with("Test = ( run = ( a := z + [ :b | z ] value ) )", interner);
+        let run = interner.get("run").unwrap();
+        assert_eq!(body(&class, run), body(&expected, run));
+    }
+
+    #[test]
+    fn renaming_methods_updates_lookup() {
+        let (mut class, mut interner) = parse("Test = ( run = ( ^ 1 ) stop = ( ^ 2 ) )");
+        let (run, start) = (interner.get("run").unwrap(), interner.intern("start"));
+        RenameMethod(run, start).visit_class_mut(&mut class).unwrap();
+        assert!(!class.instance_methods.contains_key(&run));
+        let (expected, interner) = parse_with("Test = ( start = ( ^ 1 ) )", interner);
+        assert_eq!(body(&class, start), body(&expected, start));
+        assert_eq!(class.instance_methods.names().collect::<Vec<_>>(), vec![start, interner.get("stop").unwrap()]);
+    }
+
+    #[test]
+    fn renaming_onto_existing_selector_reports_collision() {
+        let (mut class, interner) = parse("Test = ( run = ( ^ 1 ) stop = ( ^ 2 ) )");
+        let (run, stop) = (interner.get("run").unwrap(), interner.get("stop").unwrap());
+        let (expected, _) = parse_with("Test = ( stop = ( ^ 1 ) )", interner);
+        let duplicates = RenameMethod(run, stop).visit_class_mut(&mut class).unwrap_err();
+        assert_eq!(duplicates.iter().map(Method::name).collect::<Vec<_>>(), vec![stop]);
+        assert_eq!(class.instance_methods.len(), 1);
+        assert_eq!(body(&class, stop), body(&expected, stop));
+    }
+
+    #[test]
+    fn fold_rebuilds_tree() {
+        let (class, interner) = parse("Test = ( run = ( ^ x at: 1 + 2 + 3 put: [ 4 + 5 ] ) )");
+        let class = ConstantFolder(interner.get("+").unwrap()).fold_class(class).unwrap();
+        let (expected, interner) = parse_with("Test = ( run = ( ^ x at: 6 put: [ 9 ] ) )", interner);
+        let run = interner.get("run").unwrap();
+        assert_eq!(body(&class, run), body(&expected, run));
+    }
+}
@@ -1,2 +1,6 @@
+extern crate num_bigint;
+#[cfg(test)]
+extern crate quickcheck;
+
 pub mod compiler;
 mod util;
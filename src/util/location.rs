@@ -0,0 +1,40 @@
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Location(pub usize, pub usize, pub usize);
+
+impl Location {
+    pub fn line(&self) -> usize {
+        self.0
+    }
+
+    pub fn column(&self) -> usize {
+        self.1
+    }
+
+    pub fn offset(&self) -> usize {
+        self.2
+    }
+}
+
+#[derive(Copy, Clone, Default, PartialEq)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    pub fn new(start: Location, end: Location) -> Span {
+        Span { start, end }
+    }
+
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}..{}:{}", self.start.0, self.start.1, self.end.0, self.end.1)
+    }
+}
@@ -1,3 +1 @@
-pub use self::peekable_buffer::PeekableBuffer;
-
-pub mod peekable_buffer;
+pub mod location;
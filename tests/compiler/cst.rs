@@ -0,0 +1,22 @@
+use som::compiler::cst::SyntaxTree;
+use som::compiler::{Parser, ParserOptions};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn lower_test_cases() {
+    let test_cases_dir = Path::new(file!()).parent().unwrap().join("parser").join("test_cases");
+    for entry in fs::read_dir(test_cases_dir).unwrap() {
+        let entry = entry.unwrap();
+        if entry.path().extension().unwrap() == "som" {
+            let source = fs::read_to_string(entry.path()).unwrap();
+            let tree = SyntaxTree::parse(&source, ParserOptions::default()).unwrap();
+            assert_eq!(tree.text(), source);
+
+            let mut parser = Parser::new(source.as_bytes(), "test").with_trivia();
+            let class = parser.parse_class();
+            let mut interner = parser.into_interner();
+            assert_eq!(tree.lower(&mut interner), class, "{}", entry.path().display());
+        }
+    }
+}
@@ -1 +1,3 @@
+mod cst;
 mod parser;
+mod printer;
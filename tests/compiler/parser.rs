@@ -14,7 +14,7 @@ fn parse_test_cases() {
             let source = BufReader::new(File::open(&computed_file).unwrap());
             let mut parser = Parser::new(source, &computed_file);
             let class = parser.parse_class().unwrap();
-            let computed_ast = format!("{:#?}", class);
+            let computed_ast = format!("{:#?}", parser.interner().debug(&class));
 
             let given_file = computed_file.with_extension("som.ast");
             let mut given_ast = String::new();
@@ -24,3 +24,27 @@ fn parse_test_cases() {
         }
     }
 }
+
+#[test]
+fn parse_test_cases_with_trivia() {
+    let test_cases_dir = Path::new(file!()).parent().unwrap().join("parser").join("test_cases");
+    for entry in fs::read_dir(test_cases_dir).unwrap() {
+        let entry = entry.unwrap();
+        if entry.path().extension().unwrap() == "som" {
+            let computed_file = entry.path();
+            let source = BufReader::new(File::open(&computed_file).unwrap());
+            let mut parser = Parser::new(source, &computed_file).with_trivia();
+            let class = parser.parse_class().unwrap();
+            let computed_ast = format!("{:#?}", parser.interner().debug(&class));
+
+            let given_file = computed_file.with_extension("som.trivia.ast");
+            let mut given_ast = String::new();
+            match File::open(&given_file) {
+                Ok(mut file) => file.read_to_string(&mut given_ast).unwrap(),
+                Err(_) => continue,
+            };
+
+            assert_eq!(computed_ast.trim(), given_ast.trim());
+        }
+    }
+}
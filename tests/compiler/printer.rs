@@ -0,0 +1,24 @@
+use som::compiler::printer::print_class;
+use som::compiler::{Parser, ParserOptions};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn format_test_cases() {
+    let test_cases_dir = Path::new(file!()).parent().unwrap().join("parser").join("test_cases");
+    for entry in fs::read_dir(test_cases_dir).unwrap() {
+        let entry = entry.unwrap();
+        if entry.path().extension().unwrap() == "som" {
+            let source = fs::read_to_string(entry.path()).unwrap();
+            let mut parser = Parser::new(source.as_bytes(), "test").with_trivia();
+            let class = parser.parse_class().unwrap();
+            let interner = parser.into_interner();
+            let formatted = print_class(&class, &interner, &ParserOptions::default()).unwrap();
+
+            let mut parser = Parser::new(formatted.as_bytes(), "test").with_interner(interner).with_trivia();
+            let reparsed = parser.parse_class().unwrap();
+            assert_eq!(reparsed.without_spans(), class.without_spans());
+            assert_eq!(print_class(&reparsed, parser.interner(), &ParserOptions::default()).unwrap(), formatted);
+        }
+    }
+}